@@ -0,0 +1,55 @@
+// 对比预览帧两种路径的开销：
+// reencode    —— 每帧用 OpenCV 重新编码 JPEG（变换或预处理后的帧）
+// passthrough —— 直接转发设备发来的原始 JPEG（无变换的帧）
+// 运行：cargo bench --features bench --bench jpeg_passthrough
+use std::time::Instant;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use opencv::{core::{Mat, Vec3b, Vector, CV_8UC3}, imgcodecs, prelude::*};
+use papertracker_lib::Frame;
+
+// 设备常见分辨率
+const SIZES: &[(i32, i32)] = &[(240, 240), (320, 240), (640, 480)];
+
+// 带渐变和纹理的合成图像，压缩率接近真实画面
+fn synthetic_image(width: i32, height: i32) -> Mat {
+    let mut image = Mat::new_rows_cols_with_default(height, width, CV_8UC3, opencv::core::Scalar::all(0.0)).unwrap();
+    for row in 0..height {
+        for col in 0..width {
+            let texture = ((row * 7 + col * 13) % 31) as u8;
+            let value = ((row + col) * 255 / (width + height)) as u8;
+            *image.at_2d_mut::<Vec3b>(row, col).unwrap() = Vec3b::from_array([value, value.wrapping_add(texture), value]);
+        }
+    }
+    image
+}
+
+fn device_jpeg(image: &Mat) -> Vec<u8> {
+    let mut encoded = Vector::<u8>::new();
+    imgcodecs::imencode(".jpg", image, &mut encoded, &Vector::<i32>::new()).unwrap();
+    encoded.into()
+}
+
+fn preview_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("preview_jpeg");
+    for &(width, height) in SIZES {
+        let image = synthetic_image(width, height);
+        let jpeg = device_jpeg(&image);
+        let label = format!("{}x{}", width, height);
+        group.bench_with_input(BenchmarkId::new("reencode", &label), &image, |b, image| {
+            b.iter(|| {
+                let frame = Frame::new(image.clone(), Instant::now());
+                black_box(frame.encoded_jpeg().unwrap())
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("passthrough", &label), &(image, jpeg), |b, (image, jpeg)| {
+            b.iter(|| {
+                let frame = Frame::with_jpeg(image.clone(), Instant::now(), jpeg.clone());
+                black_box(frame.encoded_jpeg().unwrap())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, preview_encoding);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+macro_rules! expressions {
+    ($($variant:ident => $id:literal),* $(,)?) => {
+        // 表情参数的唯一定义，与 ARKit blendshape 对齐
+        // 配置文件中使用 snake_case（配置库会把键名转为小写），界面使用 id() 返回的 ARKit 名称
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        pub enum Expression {
+            $($variant,)*
+        }
+
+        impl Expression {
+            // 面捕模型输出顺序
+            pub const ALL: &'static [Expression] = &[$(Expression::$variant,)*];
+
+            pub fn id(&self) -> &'static str {
+                match self {
+                    $(Expression::$variant => $id,)*
+                }
+            }
+        }
+    };
+}
+
+expressions! {
+    CheekPuffLeft => "cheekPuffLeft",
+    CheekPuffRight => "cheekPuffRight",
+    CheekSuckLeft => "cheekSuckLeft",
+    CheekSuckRight => "cheekSuckRight",
+    JawOpen => "jawOpen",
+    JawForward => "jawForward",
+    JawLeft => "jawLeft",
+    JawRight => "jawRight",
+    NoseSneerLeft => "noseSneerLeft",
+    NoseSneerRight => "noseSneerRight",
+    MouthFunnel => "mouthFunnel",
+    MouthPucker => "mouthPucker",
+    MouthLeft => "mouthLeft",
+    MouthRight => "mouthRight",
+    MouthRollUpper => "mouthRollUpper",
+    MouthRollLower => "mouthRollLower",
+    MouthShrugUpper => "mouthShrugUpper",
+    MouthShrugLower => "mouthShrugLower",
+    MouthClose => "mouthClose",
+    MouthSmileLeft => "mouthSmileLeft",
+    MouthSmileRight => "mouthSmileRight",
+    MouthFrownLeft => "mouthFrownLeft",
+    MouthFrownRight => "mouthFrownRight",
+    MouthDimpleLeft => "mouthDimpleLeft",
+    MouthDimpleRight => "mouthDimpleRight",
+    MouthUpperUpLeft => "mouthUpperUpLeft",
+    MouthUpperUpRight => "mouthUpperUpRight",
+    MouthLowerDownLeft => "mouthLowerDownLeft",
+    MouthLowerDownRight => "mouthLowerDownRight",
+    MouthPressLeft => "mouthPressLeft",
+    MouthPressRight => "mouthPressRight",
+    MouthStretchLeft => "mouthStretchLeft",
+    MouthStretchRight => "mouthStretchRight",
+    TongueOut => "tongueOut",
+    TongueUp => "tongueUp",
+    TongueDown => "tongueDown",
+    TongueLeft => "tongueLeft",
+    TongueRight => "tongueRight",
+    TongueRoll => "tongueRoll",
+    TongueBendDown => "tongueBendDown",
+    TongueCurlUp => "tongueCurlUp",
+    TongueSquish => "tongueSquish",
+    TongueFlat => "tongueFlat",
+    TongueTwistLeft => "tongueTwistLeft",
+    TongueTwistRight => "tongueTwistRight",
+}
+
+impl Expression {
+    // 接受 ARKit 名称、配置中的 snake_case 名称，以及界面上的旧名称
+    // 配置库会把键名转为小写，因此不区分大小写
+    pub fn from_id(id: &str) -> Option<Expression> {
+        if id.eq_ignore_ascii_case("cheekLeft") {
+            return Some(Expression::CheekPuffLeft);
+        }
+        if id.eq_ignore_ascii_case("cheekRight") {
+            return Some(Expression::CheekPuffRight);
+        }
+        Expression::ALL.iter().copied().find(|expression| {
+            expression.id().eq_ignore_ascii_case(id) || expression.config_key() == id
+        })
+    }
+
+    // 配置文件中的键名，如 cheek_puff_left
+    pub fn config_key(&self) -> String {
+        let mut key = String::new();
+        for c in self.id().chars() {
+            if c.is_ascii_uppercase() {
+                key.push('_');
+                key.push(c.to_ascii_lowercase());
+            } else {
+                key.push(c);
+            }
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_id_accepts_all_names() {
+        for expression in Expression::ALL {
+            assert_eq!(Expression::from_id(expression.id()), Some(*expression));
+            assert_eq!(Expression::from_id(&expression.config_key()), Some(*expression));
+            // 经过配置库后的小写 ARKit 名称
+            assert_eq!(Expression::from_id(&expression.id().to_ascii_lowercase()), Some(*expression));
+        }
+        assert_eq!(Expression::from_id("unknown"), None);
+    }
+
+    #[test]
+    fn from_id_accepts_ui_aliases_in_any_case() {
+        assert_eq!(Expression::from_id("cheekLeft"), Some(Expression::CheekPuffLeft));
+        assert_eq!(Expression::from_id("cheekleft"), Some(Expression::CheekPuffLeft));
+        assert_eq!(Expression::from_id("CHEEKRIGHT"), Some(Expression::CheekPuffRight));
+    }
+
+    #[test]
+    fn config_key_is_snake_case() {
+        assert_eq!(Expression::CheekPuffLeft.config_key(), "cheek_puff_left");
+        assert_eq!(Expression::MouthUpperUpRight.config_key(), "mouth_upper_up_right");
+        for expression in Expression::ALL {
+            assert_eq!(serde_json::to_string(expression).unwrap(), format!("\"{}\"", expression.config_key()));
+        }
+    }
+}
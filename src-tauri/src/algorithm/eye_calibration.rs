@@ -0,0 +1,344 @@
+use serde::Serialize;
+use crate::paper_tracker_config::config::EyeParamsConfig;
+use crate::utils::consts::DEVICE_TYPE_LEFT_EYE;
+use super::eye_tracker::EyeObservation;
+
+// 低于这些值的帧不作为校准样本（眨眼、检测失败）
+const MIN_SAMPLE_CONFIDENCE: f32 = 0.5;
+const MIN_SAMPLE_OPENNESS: f32 = 0.3;
+// 每个阶段至少需要的样本数
+const MIN_LOOK_AROUND_SAMPLES: usize = 30;
+const MIN_CENTER_SAMPLES: usize = 10;
+// 环视范围取分位数，去掉偶尔的误检
+const RANGE_PERCENTILE: f64 = 0.05;
+// 中心到边缘的最小距离，过小说明没有环视
+const MIN_HALF_RANGE: f64 = 0.05;
+
+// 校准后的视线映射，以注视正前方时的原始视线为中心，中心到环视边缘映射为 [-1, 1]
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct EyeCalibration {
+    pub xmin: f64,
+    pub xmax: f64,
+    pub ymin: f64,
+    pub ymax: f64,
+    pub xoff: f64,
+    pub yoff: f64,
+}
+
+fn map_axis(value: f64, min: f64, max: f64, center: f64) -> f64 {
+    let mapped = if value >= center {
+        (value - center) / (max - center).max(MIN_HALF_RANGE)
+    } else {
+        (value - center) / (center - min).max(MIN_HALF_RANGE)
+    };
+    mapped.clamp(-1.0, 1.0)
+}
+
+impl EyeCalibration {
+    // 没有校准数据时返回 None
+    pub fn from_params(params: &EyeParamsConfig, device_type: i32) -> Option<Self> {
+        if device_type == DEVICE_TYPE_LEFT_EYE {
+            if !params.left_has_calibration {
+                return None;
+            }
+            Some(EyeCalibration {
+                xmin: params.left_calib_xmin,
+                xmax: params.left_calib_xmax,
+                ymin: params.left_calib_ymin,
+                ymax: params.left_calib_ymax,
+                xoff: params.left_calib_xoff,
+                yoff: params.left_calib_yoff,
+            })
+        } else {
+            if !params.right_has_calibration {
+                return None;
+            }
+            Some(EyeCalibration {
+                xmin: params.right_calib_xmin,
+                xmax: params.right_calib_xmax,
+                ymin: params.right_calib_ymin,
+                ymax: params.right_calib_ymax,
+                xoff: params.right_calib_xoff,
+                yoff: params.right_calib_yoff,
+            })
+        }
+    }
+
+    pub fn store(&self, params: &mut EyeParamsConfig, device_type: i32) {
+        if device_type == DEVICE_TYPE_LEFT_EYE {
+            params.left_calib_xmin = self.xmin;
+            params.left_calib_xmax = self.xmax;
+            params.left_calib_ymin = self.ymin;
+            params.left_calib_ymax = self.ymax;
+            params.left_calib_xoff = self.xoff;
+            params.left_calib_yoff = self.yoff;
+            params.left_has_calibration = true;
+        } else {
+            params.right_calib_xmin = self.xmin;
+            params.right_calib_xmax = self.xmax;
+            params.right_calib_ymin = self.ymin;
+            params.right_calib_ymax = self.ymax;
+            params.right_calib_xoff = self.xoff;
+            params.right_calib_yoff = self.yoff;
+            params.right_has_calibration = true;
+        }
+    }
+
+    pub fn apply(&self, gaze_x: f32, gaze_y: f32) -> (f32, f32) {
+        (
+            map_axis(gaze_x as f64, self.xmin, self.xmax, self.xoff) as f32,
+            map_axis(gaze_y as f64, self.ymin, self.ymax, self.yoff) as f32,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationStep {
+    // 引导用户缓慢环视四周，记录视线范围
+    #[default]
+    LookAround,
+    // 注视正前方，记录中心
+    Center,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EyeCalibrationStatus {
+    pub step: CalibrationStep,
+    pub look_around_samples: usize,
+    pub center_samples: usize,
+    // 界面据此判断能否进入下一步
+    pub look_around_required: usize,
+    pub center_required: usize,
+}
+
+// 一次校准过程，由眼追线程写入未经校准的视线
+#[derive(Debug, Clone, Default)]
+pub struct EyeCalibrationSession {
+    step: CalibrationStep,
+    // 只重新设置中心时沿用已有的范围
+    base: Option<EyeCalibration>,
+    look_around: Vec<(f64, f64)>,
+    center: Vec<(f64, f64)>,
+}
+
+fn percentile(values: &mut [f64], p: f64) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let index = ((values.len() - 1) as f64 * p).round() as usize;
+    values[index]
+}
+
+impl EyeCalibrationSession {
+    pub fn new() -> Self {
+        EyeCalibrationSession::default()
+    }
+
+    // 只重新采集中心，范围使用已有校准
+    pub fn center_only(base: EyeCalibration) -> Self {
+        EyeCalibrationSession {
+            step: CalibrationStep::Center,
+            base: Some(base),
+            look_around: Vec::new(),
+            center: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, observation: &EyeObservation) {
+        if observation.confidence < MIN_SAMPLE_CONFIDENCE || observation.openness < MIN_SAMPLE_OPENNESS {
+            return;
+        }
+        let sample = (observation.gaze_x as f64, observation.gaze_y as f64);
+        match self.step {
+            CalibrationStep::LookAround => self.look_around.push(sample),
+            CalibrationStep::Center => self.center.push(sample),
+        }
+    }
+
+    pub fn status(&self) -> EyeCalibrationStatus {
+        EyeCalibrationStatus {
+            step: self.step,
+            look_around_samples: self.look_around.len(),
+            center_samples: self.center.len(),
+            look_around_required: MIN_LOOK_AROUND_SAMPLES,
+            center_required: MIN_CENTER_SAMPLES,
+        }
+    }
+
+    pub fn next_step(&mut self) -> Result<(), String> {
+        match self.step {
+            CalibrationStep::LookAround => {
+                if self.look_around.len() < MIN_LOOK_AROUND_SAMPLES {
+                    return Err(format!("环视样本不足: {} / {}", self.look_around.len(), MIN_LOOK_AROUND_SAMPLES));
+                }
+                self.step = CalibrationStep::Center;
+                Ok(())
+            }
+            CalibrationStep::Center => Err("已经是最后一步".to_string()),
+        }
+    }
+
+    pub fn finish(&self) -> Result<EyeCalibration, String> {
+        if self.step != CalibrationStep::Center {
+            return Err("还未完成环视".to_string());
+        }
+        if self.center.len() < MIN_CENTER_SAMPLES {
+            return Err(format!("中心样本不足: {} / {}", self.center.len(), MIN_CENTER_SAMPLES));
+        }
+        let mut center_x: Vec<f64> = self.center.iter().map(|sample| sample.0).collect();
+        let mut center_y: Vec<f64> = self.center.iter().map(|sample| sample.1).collect();
+        let xoff = percentile(&mut center_x, 0.5);
+        let yoff = percentile(&mut center_y, 0.5);
+        let calibration = match self.base {
+            Some(base) => EyeCalibration { xoff, yoff, ..base },
+            None => {
+                let mut xs: Vec<f64> = self.look_around.iter().map(|sample| sample.0).collect();
+                let mut ys: Vec<f64> = self.look_around.iter().map(|sample| sample.1).collect();
+                EyeCalibration {
+                    xmin: percentile(&mut xs, RANGE_PERCENTILE),
+                    xmax: percentile(&mut xs, 1.0 - RANGE_PERCENTILE),
+                    ymin: percentile(&mut ys, RANGE_PERCENTILE),
+                    ymax: percentile(&mut ys, 1.0 - RANGE_PERCENTILE),
+                    xoff,
+                    yoff,
+                }
+            }
+        };
+        if calibration.xmax - calibration.xoff < MIN_HALF_RANGE
+            || calibration.xoff - calibration.xmin < MIN_HALF_RANGE
+            || calibration.ymax - calibration.yoff < MIN_HALF_RANGE
+            || calibration.yoff - calibration.ymin < MIN_HALF_RANGE
+        {
+            return Err("视线范围过小或中心偏离，请重新校准".to_string());
+        }
+        Ok(calibration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(gaze_x: f64, gaze_y: f64, confidence: f32, openness: f32) -> EyeObservation {
+        EyeObservation {
+            gaze_x: gaze_x as f32,
+            gaze_y: gaze_y as f32,
+            openness,
+            confidence,
+            pupil: None,
+        }
+    }
+
+    fn sample(gaze_x: f64, gaze_y: f64) -> EyeObservation {
+        observation(gaze_x, gaze_y, 1.0, 1.0)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    fn push_center(session: &mut EyeCalibrationSession, gaze_x: f64, gaze_y: f64) {
+        for i in 0..MIN_CENTER_SAMPLES as i32 + 1 {
+            let jitter = (i - MIN_CENTER_SAMPLES as i32 / 2) as f64 * 0.001;
+            session.push(&sample(gaze_x + jitter, gaze_y - jitter));
+        }
+    }
+
+    #[test]
+    fn full_calibration_uses_percentile_range_and_median_center() {
+        let mut session = EyeCalibrationSession::new();
+        // 101 个均匀样本，两端换成误检的离群值，分位数不受影响
+        for i in 0..=100 {
+            let (gaze_x, gaze_y) = match i {
+                0 => (-5.0, -5.0),
+                100 => (5.0, 5.0),
+                _ => ((i - 50) as f64 / 100.0, (i - 50) as f64 / 200.0),
+            };
+            session.push(&sample(gaze_x, gaze_y));
+        }
+        session.next_step().unwrap();
+        push_center(&mut session, 0.02, -0.01);
+        let calibration = session.finish().unwrap();
+        assert_close(calibration.xmin, -0.45);
+        assert_close(calibration.xmax, 0.45);
+        assert_close(calibration.ymin, -0.225);
+        assert_close(calibration.ymax, 0.225);
+        assert_close(calibration.xoff, 0.02);
+        assert_close(calibration.yoff, -0.01);
+
+        // 中心映射为 0，环视边缘映射为 ±1，两侧分别缩放
+        let (x, y) = calibration.apply(0.02, -0.01);
+        assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+        let (x, y) = calibration.apply(0.45, -0.225);
+        assert!((x - 1.0).abs() < 1e-5 && (y + 1.0).abs() < 1e-5);
+        let (x, _) = calibration.apply(-0.215, 0.0);
+        assert!((x + 0.5).abs() < 1e-5);
+        assert_eq!(calibration.apply(2.0, 2.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn center_only_keeps_base_range() {
+        let base = EyeCalibration { xmin: -0.4, xmax: 0.4, ymin: -0.3, ymax: 0.3, xoff: 0.0, yoff: 0.0 };
+        let mut session = EyeCalibrationSession::center_only(base);
+        assert_eq!(session.status().step, CalibrationStep::Center);
+        assert!(session.next_step().is_err());
+        push_center(&mut session, 0.1, 0.05);
+        let calibration = session.finish().unwrap();
+        assert_eq!((calibration.xmin, calibration.xmax, calibration.ymin, calibration.ymax), (-0.4, 0.4, -0.3, 0.3));
+        assert_close(calibration.xoff, 0.1);
+        assert_close(calibration.yoff, 0.05);
+    }
+
+    #[test]
+    fn rejects_low_confidence_and_closed_eye_samples() {
+        let mut session = EyeCalibrationSession::new();
+        session.push(&observation(0.1, 0.1, MIN_SAMPLE_CONFIDENCE - 0.1, 1.0));
+        session.push(&observation(0.1, 0.1, 1.0, MIN_SAMPLE_OPENNESS - 0.1));
+        assert_eq!(session.status().look_around_samples, 0);
+        session.push(&observation(0.1, 0.1, MIN_SAMPLE_CONFIDENCE, MIN_SAMPLE_OPENNESS));
+        assert_eq!(session.status().look_around_samples, 1);
+    }
+
+    #[test]
+    fn steps_require_enough_samples() {
+        let mut session = EyeCalibrationSession::new();
+        assert!(session.finish().is_err());
+        for _ in 0..MIN_LOOK_AROUND_SAMPLES - 1 {
+            session.push(&sample(0.0, 0.0));
+        }
+        assert!(session.next_step().is_err());
+        // 界面用 status 中的所需样本数判断何时进入下一步
+        let status = session.status();
+        assert!(status.look_around_samples < status.look_around_required);
+        session.push(&sample(0.0, 0.0));
+        assert_eq!(session.status().look_around_samples, session.status().look_around_required);
+        session.next_step().unwrap();
+        session.push(&sample(0.0, 0.0));
+        assert!(session.finish().is_err());
+    }
+
+    #[test]
+    fn small_range_is_an_error() {
+        let mut session = EyeCalibrationSession::new();
+        // 几乎没有环视
+        for i in 0..MIN_LOOK_AROUND_SAMPLES {
+            let offset = (i as f64 / MIN_LOOK_AROUND_SAMPLES as f64 - 0.5) * 0.06;
+            session.push(&sample(offset, offset));
+        }
+        session.next_step().unwrap();
+        push_center(&mut session, 0.0, 0.0);
+        assert!(session.finish().is_err());
+    }
+
+    #[test]
+    fn off_center_fixation_is_an_error() {
+        let mut session = EyeCalibrationSession::new();
+        for i in 0..=100 {
+            let offset = (i - 50) as f64 / 100.0;
+            session.push(&sample(offset, offset));
+        }
+        session.next_step().unwrap();
+        // 中心落在环视边缘，一侧没有余量
+        push_center(&mut session, 0.44, 0.0);
+        assert!(session.finish().is_err());
+    }
+}
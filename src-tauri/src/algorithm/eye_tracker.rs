@@ -0,0 +1,360 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::{Duration, Instant}};
+use crossbeam::channel::RecvTimeoutError;
+use opencv::core::{Mat, MatTraitConst};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+use ftlog::*;
+use crate::paper_tracker_config::config::EYE_CONFIG;
+use crate::utils::consts::DEVICE_TYPE_LEFT_EYE;
+use crate::websocket::{frame_hub::{FrameHub, FrameSubscription, SubscriberPolicy}, image_msg::Frame, latency::{FrameTiming, LatencyStage, LatencyStats}, stereo_sync::StereoFrame};
+use super::{eye_calibration::{EyeCalibration, EyeCalibrationSession}, filter::{filter_settings, FilterBank}, onnxrt_inference::{gray_tensor, resolve_model_path, Inference, MODEL_RETRY_INTERVAL}, pupil_detector::{PupilDetector, PupilDetectorConfig, PupilFit}};
+
+pub const EYE_MODEL_NAME: &str = "eye_model.onnx";
+const EYE_INPUT_SIZE: i32 = 112;
+// 推送给界面的最小间隔
+const UI_EMIT_INTERVAL: Duration = Duration::from_millis(33);
+
+// 单帧检测结果，gaze 以图像中心为 0，范围 [-1, 1]，向右、向下为正
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct EyeObservation {
+    pub gaze_x: f32,
+    pub gaze_y: f32,
+    // 0 为闭眼，1 为完全睁开
+    pub openness: f32,
+    pub confidence: f32,
+    // 传统检测器的瞳孔拟合结果，模型检测时为空
+    pub pupil: Option<PupilFit>,
+}
+
+// 每只眼单独选择的检测方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EyeDetectorKind {
+    #[default]
+    Model,
+    // 不需要模型的瞳孔检测，适合没有模型或 CPU 较弱的情况
+    Pupil,
+}
+
+// 输入为已裁剪到 ROI 的眼部图像
+pub trait EyeDetector: Send {
+    fn name(&self) -> &str;
+
+    fn detect(&mut self, image: &Mat) -> anyhow::Result<EyeObservation>;
+}
+
+// 模型输出 [x, y, openness] 或 [x, y, openness, confidence]，x/y 为 [0, 1] 的图像坐标
+pub struct ModelEyeDetector {
+    inference: Inference,
+}
+
+impl ModelEyeDetector {
+    pub fn new<R: Runtime>(app: &AppHandle<R>, threads: usize) -> anyhow::Result<Self> {
+        let path = resolve_model_path(app, EYE_MODEL_NAME)?;
+        let inference = Inference::new(&path, threads)?;
+        inference.validate(
+            &[None, Some(1), Some(EYE_INPUT_SIZE as usize), Some(EYE_INPUT_SIZE as usize)],
+            &[&[None, None]],
+        )?;
+        Ok(ModelEyeDetector { inference })
+    }
+}
+
+impl EyeDetector for ModelEyeDetector {
+    fn name(&self) -> &str {
+        "model"
+    }
+
+    fn detect(&mut self, image: &Mat) -> anyhow::Result<EyeObservation> {
+        let rect = opencv::core::Rect::new(0, 0, image.cols(), image.rows());
+        let input = gray_tensor(image, rect, EYE_INPUT_SIZE, EYE_INPUT_SIZE)?;
+        let outputs = self.inference.run(input)?;
+        let output = outputs.into_iter().next().ok_or_else(|| anyhow::anyhow!("eye model returned no output"))?;
+        if output.data.len() < 3 {
+            return Err(anyhow::anyhow!("eye model returned {} values, expected at least 3", output.data.len()));
+        }
+        Ok(EyeObservation {
+            gaze_x: (output.data[0] * 2.0 - 1.0).clamp(-1.0, 1.0),
+            gaze_y: (output.data[1] * 2.0 - 1.0).clamp(-1.0, 1.0),
+            openness: output.data[2].clamp(0.0, 1.0),
+            confidence: output.data.get(3).copied().unwrap_or(1.0).clamp(0.0, 1.0),
+            pupil: None,
+        })
+    }
+}
+
+// 一帧的眼追结果
+#[derive(Debug, Clone, Serialize)]
+pub struct EyeState {
+    pub device_type: i32,
+    #[serde(flatten)]
+    pub observation: EyeObservation,
+    #[serde(skip)]
+    pub timing: FrameTiming,
+}
+
+enum EyeSubscription {
+    Single(FrameSubscription),
+    Stereo(FrameSubscription<Arc<StereoFrame>>),
+}
+
+// 眼追的图像输入：左右眼都在线时从双目配对流中取本眼的帧，保证两眼结果来自同一时刻
+// 只有一只眼在线时直接订阅单眼图像，此时配对流没有订阅者，不会占用另一路
+pub struct EyeFrameInput {
+    device_type: i32,
+    frame_hub: FrameHub,
+    stereo_hub: FrameHub<Arc<StereoFrame>>,
+    left_connected: Arc<AtomicBool>,
+    right_connected: Arc<AtomicBool>,
+    subscription: Option<EyeSubscription>,
+}
+
+impl EyeFrameInput {
+    pub fn new(
+        device_type: i32,
+        frame_hub: FrameHub,
+        stereo_hub: FrameHub<Arc<StereoFrame>>,
+        left_connected: Arc<AtomicBool>,
+        right_connected: Arc<AtomicBool>,
+    ) -> Self {
+        EyeFrameInput {
+            device_type,
+            frame_hub,
+            stereo_hub,
+            left_connected,
+            right_connected,
+            subscription: None,
+        }
+    }
+
+    fn stereo_available(&self) -> bool {
+        self.left_connected.load(Ordering::Relaxed) && self.right_connected.load(Ordering::Relaxed)
+    }
+
+    // 退订图像，没有其他订阅者时图像流可以暂停解码
+    pub fn pause(&mut self) {
+        if self.subscription.take().is_some() {
+            info!("Eye tracker {} paused", self.device_type);
+        }
+    }
+
+    // 每次读取前检查两眼是否在线，需要时切换订阅
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Arc<Frame>, RecvTimeoutError> {
+        let device_type = self.device_type;
+        let stereo = self.stereo_available();
+        let name = format!("eye_tracker:{}", device_type);
+        let subscription = match self.subscription.take() {
+            Some(EyeSubscription::Stereo(subscription)) if stereo => EyeSubscription::Stereo(subscription),
+            Some(EyeSubscription::Single(subscription)) if !stereo => EyeSubscription::Single(subscription),
+            _ if stereo => {
+                info!("Eye tracker {} using paired stereo frames", device_type);
+                EyeSubscription::Stereo(self.stereo_hub.subscribe(&name, SubscriberPolicy::LatestOnly))
+            }
+            _ => {
+                info!("Eye tracker {} using single eye frames", device_type);
+                EyeSubscription::Single(self.frame_hub.subscribe(&name, SubscriberPolicy::LatestOnly))
+            }
+        };
+        match self.subscription.insert(subscription) {
+            EyeSubscription::Single(subscription) => subscription.recv_timeout(timeout),
+            EyeSubscription::Stereo(subscription) => subscription.recv_timeout(timeout).map(|pair| {
+                if device_type == DEVICE_TYPE_LEFT_EYE {
+                    pair.left.clone()
+                } else {
+                    pair.right.clone()
+                }
+            }),
+        }
+    }
+}
+
+// 推送给界面的检测器状态，选择了模型但模型不可用时 fallback 为 true，error 为原因
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EyeDetectorStatus {
+    pub device_type: i32,
+    // 实际使用的检测方式
+    pub detector: EyeDetectorKind,
+    pub fallback: bool,
+    pub error: Option<String>,
+}
+
+// 订阅单眼图像，每帧检测视线和睁眼程度后发布给各个消费者，并推送到界面
+pub struct EyeTracker<R: Runtime> {
+    device_type: i32,
+    input: EyeFrameInput,
+    result_hub: FrameHub<Arc<EyeState>>,
+    latency: Arc<Mutex<LatencyStats>>,
+    // 最近一次推送给界面的检测器状态，相同状态不重复推送
+    detector_status: Option<EyeDetectorStatus>,
+    // 校准进行中时写入未经校准的视线
+    calibration_session: Arc<Mutex<Option<EyeCalibrationSession>>>,
+    // 依次为 gaze_x、gaze_y、openness
+    filter: FilterBank,
+    // 上一次可信的视线（滤波后、校准前）
+    last_gaze: Option<(f32, f32)>,
+    app_handle: AppHandle<R>,
+}
+
+impl<R: Runtime> EyeTracker<R> {
+    pub fn new(input: EyeFrameInput, latency: Arc<Mutex<LatencyStats>>, app: AppHandle<R>) -> Self {
+        EyeTracker {
+            device_type: input.device_type,
+            input,
+            result_hub: FrameHub::new(),
+            latency,
+            detector_status: None,
+            calibration_session: Arc::new(Mutex::new(None)),
+            filter: FilterBank::new(),
+            last_gaze: None,
+            app_handle: app,
+        }
+    }
+
+    pub fn get_result_hub(&self) -> FrameHub<Arc<EyeState>> {
+        self.result_hub.clone()
+    }
+
+    pub fn get_calibration_session(&self) -> Arc<Mutex<Option<EyeCalibrationSession>>> {
+        self.calibration_session.clone()
+    }
+
+    // 只有开启了眼追时才订阅图像，否则图像流可以暂停解码
+    pub fn start(&mut self) {
+        let (mut kind, mut pupil_config) = self.detector_config();
+        let mut detector = self.create_detector(kind, &pupil_config);
+        let mut last_load = Instant::now();
+        let mut last_emit: Option<Instant> = None;
+        loop {
+            // 检测方式或参数修改后重新创建检测器，退回瞳孔检测期间定期重试加载模型
+            let (new_kind, new_pupil_config) = self.detector_config();
+            let retry = self.in_fallback() && last_load.elapsed() >= MODEL_RETRY_INTERVAL;
+            if new_kind != kind || new_pupil_config != pupil_config || retry {
+                kind = new_kind;
+                pupil_config = new_pupil_config;
+                detector = self.create_detector(kind, &pupil_config);
+                last_load = Instant::now();
+            }
+            if !EYE_CONFIG.read().unwrap().functional.tracking {
+                self.input.pause();
+                std::thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            match self.input.recv_timeout(Duration::from_millis(200)) {
+                Ok(frame) => {
+                    let result = match self.track(detector.as_mut(), &frame) {
+                        Ok(result) => Arc::new(result),
+                        Err(e) => {
+                            warn!("Eye tracker {} failed: {}", self.device_type, e);
+                            continue;
+                        }
+                    };
+                    let publish_start = Instant::now();
+                    self.result_hub.publish(result.clone());
+                    if last_emit.map(|last_emit| last_emit.elapsed() >= UI_EMIT_INTERVAL).unwrap_or(true) {
+                        let _ = self.app_handle.emit("eye_tracking", result.as_ref());
+                        last_emit = Some(Instant::now());
+                    }
+                    let mut latency = self.latency.lock().unwrap();
+                    latency.record(LatencyStage::Publish, publish_start.elapsed());
+                    latency.record(LatencyStage::Total, result.timing.origin().elapsed());
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    error!("Eye tracker {} frame hub disconnected", self.device_type);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn detector_config(&self) -> (EyeDetectorKind, PupilDetectorConfig) {
+        let eye_config = EYE_CONFIG.read().unwrap();
+        let kind = if self.device_type == DEVICE_TYPE_LEFT_EYE {
+            eye_config.functional.left_detector
+        } else {
+            eye_config.functional.right_detector
+        };
+        (kind, eye_config.functional.pupil_detector.clone())
+    }
+
+    fn in_fallback(&self) -> bool {
+        self.detector_status.as_ref().map(|status| status.fallback).unwrap_or(false)
+    }
+
+    // 模型加载失败时退回瞳孔检测，并把状态推送给界面
+    fn create_detector(&mut self, kind: EyeDetectorKind, pupil_config: &PupilDetectorConfig) -> Box<dyn EyeDetector> {
+        let mut error = None;
+        if kind == EyeDetectorKind::Model {
+            let threads = EYE_CONFIG.read().unwrap().functional.inference_threads;
+            match ModelEyeDetector::new(&self.app_handle, threads) {
+                Ok(detector) => {
+                    self.report_detector(EyeDetectorKind::Model, None);
+                    return Box::new(detector);
+                }
+                Err(e) => error = Some(e.to_string()),
+            }
+        }
+        self.report_detector(EyeDetectorKind::Pupil, error);
+        Box::new(PupilDetector::new(pupil_config.clone()))
+    }
+
+    fn report_detector(&mut self, detector: EyeDetectorKind, error: Option<String>) {
+        let status = EyeDetectorStatus {
+            device_type: self.device_type,
+            detector,
+            fallback: error.is_some(),
+            error,
+        };
+        if self.detector_status.as_ref() == Some(&status) {
+            return;
+        }
+        match &status.error {
+            Some(e) => warn!("Eye tracker {} model unavailable, using pupil detector and retrying every {:?}: {}", self.device_type, MODEL_RETRY_INTERVAL, e),
+            None => info!("Eye tracker {} using {:?} detector", self.device_type, detector),
+        }
+        let _ = self.app_handle.emit("eye_detector_status", &status);
+        self.detector_status = Some(status);
+    }
+
+    fn track(&mut self, detector: &mut dyn EyeDetector, frame: &Frame) -> anyhow::Result<EyeState> {
+        let (roi, min_confidence, use_filter, gaze_filter, openness_filter, calibration) = {
+            let eye_config = EYE_CONFIG.read().unwrap();
+            let functional = &eye_config.functional;
+            let roi = if self.device_type == DEVICE_TYPE_LEFT_EYE {
+                functional.left_rect.clone()
+            } else {
+                functional.right_rect.clone()
+            };
+            (roi, functional.min_confidence, functional.use_filter, functional.gaze_filter, functional.openness_filter, EyeCalibration::from_params(&eye_config.params, self.device_type))
+        };
+        let rect = roi.to_rect(frame.image.cols(), frame.image.rows());
+        let cropped = Mat::roi(&frame.image, rect)?.try_clone()?;
+        let detect_start = Instant::now();
+        let mut observation = detector.detect(&cropped)?;
+        self.latency.lock().unwrap().record(LatencyStage::Inference, detect_start.elapsed());
+        let settings = filter_settings(use_filter);
+        let kinds = [gaze_filter, gaze_filter, openness_filter];
+        // 置信度低（闭眼、反光、检测失败）时视线不可信，保持上一次的视线且不更新视线滤波，睁眼程度照常更新
+        let hold_gaze = observation.confidence < min_confidence && self.last_gaze.is_some();
+        let mut values = [observation.gaze_x, observation.gaze_y, observation.openness];
+        self.filter.apply_masked(settings.as_ref(), &kinds, &mut values, &[hold_gaze, hold_gaze, false], frame.capture_time());
+        match self.last_gaze {
+            Some((gaze_x, gaze_y)) if hold_gaze => (values[0], values[1]) = (gaze_x, gaze_y),
+            _ => self.last_gaze = Some((values[0].clamp(-1.0, 1.0), values[1].clamp(-1.0, 1.0))),
+        }
+        observation.gaze_x = values[0].clamp(-1.0, 1.0);
+        observation.gaze_y = values[1].clamp(-1.0, 1.0);
+        observation.openness = values[2].clamp(0.0, 1.0);
+        if let Some(session) = self.calibration_session.lock().unwrap().as_mut() {
+            session.push(&observation);
+        }
+        if let Some(calibration) = calibration {
+            (observation.gaze_x, observation.gaze_y) = calibration.apply(observation.gaze_x, observation.gaze_y);
+        }
+        Ok(EyeState {
+            device_type: self.device_type,
+            observation,
+            timing: frame.timing,
+        })
+    }
+}
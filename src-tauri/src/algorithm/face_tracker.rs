@@ -0,0 +1,184 @@
+use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+use crossbeam::channel::RecvTimeoutError;
+use opencv::core::MatTraitConst;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Runtime};
+use ftlog::*;
+use crate::paper_tracker_config::config::{FaceParamsConfig, FACE_CONFIG};
+use crate::websocket::{frame_hub::{FrameHub, FrameSubscription, SubscriberPolicy}, image_msg::Frame, latency::{FrameTiming, LatencyStage, LatencyStats}};
+use super::{expression::Expression, filter::{filter_settings, FilterBank}, onnxrt_inference::{gray_tensor, resolve_model_path, Inference, MODEL_RETRY_INTERVAL}};
+
+// 面捕模型约定（模型不随仓库分发，放到安装目录 assets/face_model.onnx）：
+// 输入 1 个 float 张量 [N, 1, 224, 224]，为 functional.rect 裁剪后的灰度图，像素归一化到 [0, 1]
+// 输出 1 个 float 张量 [N, Expression::ALL.len()]，顺序与 Expression::ALL 一致，数值范围约 [0, 1]
+pub const FACE_MODEL_NAME: &str = "face_model.onnx";
+const FACE_INPUT_SIZE: i32 = 224;
+// 推送给界面的最小间隔
+const UI_EMIT_INTERVAL: Duration = Duration::from_millis(33);
+
+// 一帧的表情结果，数值与 Expression::ALL 一一对应
+#[derive(Debug, Clone)]
+pub struct FaceBlendshapes {
+    pub timing: FrameTiming,
+    pub values: Vec<f32>,
+}
+
+impl FaceBlendshapes {
+    pub fn get(&self, expression: Expression) -> f32 {
+        Expression::ALL.iter()
+            .position(|item| *item == expression)
+            .and_then(|index| self.values.get(index).copied())
+            .unwrap_or(0.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Expression, f32)> + '_ {
+        Expression::ALL.iter().copied().zip(self.values.iter().copied())
+    }
+
+    pub fn named(&self) -> Vec<BlendshapeValue> {
+        self.iter().map(|(expression, value)| BlendshapeValue { name: expression.id(), value }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlendshapeValue {
+    pub name: &'static str,
+    pub value: f32,
+}
+
+// 订阅面捕图像，推理出表情后发布给各个消费者，并推送到界面
+pub struct FaceTracker<R: Runtime> {
+    frame_hub: FrameHub,
+    result_hub: FrameHub<Arc<FaceBlendshapes>>,
+    latency: Arc<Mutex<LatencyStats>>,
+    filter: FilterBank,
+    app_handle: AppHandle<R>,
+}
+
+impl<R: Runtime> FaceTracker<R> {
+    pub fn new(frame_hub: FrameHub, latency: Arc<Mutex<LatencyStats>>, app: AppHandle<R>) -> Self {
+        FaceTracker {
+            frame_hub,
+            result_hub: FrameHub::new(),
+            latency,
+            filter: FilterBank::new(),
+            app_handle: app,
+        }
+    }
+
+    pub fn get_result_hub(&self) -> FrameHub<Arc<FaceBlendshapes>> {
+        self.result_hub.clone()
+    }
+
+    // 只有模型已加载且开启了面捕时才订阅图像，否则图像流可以暂停解码
+    pub fn start(&mut self) {
+        let mut inference: Option<Inference> = None;
+        let mut last_load: Option<Instant> = None;
+        let mut last_error: Option<String> = None;
+        let mut subscription: Option<FrameSubscription> = None;
+        let mut last_emit: Option<Instant> = None;
+        loop {
+            if inference.is_none() && last_load.map(|last_load| last_load.elapsed() >= MODEL_RETRY_INTERVAL).unwrap_or(true) {
+                last_load = Some(Instant::now());
+                match self.load_model() {
+                    Ok(model) => {
+                        inference = Some(model);
+                        last_error = None;
+                    }
+                    Err(e) => {
+                        // 同样的错误只报告一次
+                        let e = e.to_string();
+                        if last_error.as_ref() != Some(&e) {
+                            error!("Face tracker model unavailable, retrying every {:?}: {}", MODEL_RETRY_INTERVAL, e);
+                            let _ = self.app_handle.emit("face_tracking_error", e.clone());
+                            last_error = Some(e);
+                        }
+                    }
+                }
+            }
+            let enabled = FACE_CONFIG.read().unwrap().functional.tracking;
+            let model = match inference.as_mut() {
+                Some(model) if enabled => model,
+                _ => {
+                    if subscription.take().is_some() {
+                        info!("Face tracker paused");
+                    }
+                    std::thread::sleep(Duration::from_millis(200));
+                    continue;
+                }
+            };
+            let frames = subscription.get_or_insert_with(|| {
+                info!("Face tracker started");
+                self.frame_hub.subscribe("face_tracker", SubscriberPolicy::LatestOnly)
+            });
+            match frames.recv_timeout(Duration::from_millis(200)) {
+                Ok(frame) => {
+                    let result = match self.track(model, &frame) {
+                        Ok(result) => Arc::new(result),
+                        Err(e) => {
+                            warn!("Face tracking failed: {}", e);
+                            continue;
+                        }
+                    };
+                    let publish_start = Instant::now();
+                    self.result_hub.publish(result.clone());
+                    if last_emit.map(|last_emit| last_emit.elapsed() >= UI_EMIT_INTERVAL).unwrap_or(true) {
+                        let _ = self.app_handle.emit("face_blendshapes", result.named());
+                        last_emit = Some(Instant::now());
+                    }
+                    let mut latency = self.latency.lock().unwrap();
+                    latency.record(LatencyStage::Publish, publish_start.elapsed());
+                    latency.record(LatencyStage::Total, result.timing.origin().elapsed());
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    error!("Face frame hub disconnected");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn load_model(&self) -> anyhow::Result<Inference> {
+        let threads = FACE_CONFIG.read().unwrap().functional.inference_threads;
+        let path = resolve_model_path(&self.app_handle, FACE_MODEL_NAME)?;
+        let inference = Inference::new(&path, threads)?;
+        inference.validate(
+            &[None, Some(1), Some(FACE_INPUT_SIZE as usize), Some(FACE_INPUT_SIZE as usize)],
+            &[&[None, Some(Expression::ALL.len())]],
+        )?;
+        Ok(inference)
+    }
+
+    fn track(&mut self, inference: &mut Inference, frame: &Frame) -> anyhow::Result<FaceBlendshapes> {
+        let rect = FACE_CONFIG.read().unwrap().functional.rect.to_rect(frame.image.cols(), frame.image.rows());
+        let input = gray_tensor(&frame.image, rect, FACE_INPUT_SIZE, FACE_INPUT_SIZE)?;
+        let inference_start = Instant::now();
+        let outputs = inference.run(input)?;
+        self.latency.lock().unwrap().record(LatencyStage::Inference, inference_start.elapsed());
+        let output = outputs.into_iter().next().ok_or_else(|| anyhow::anyhow!("face model returned no output"))?;
+        if output.data.len() != Expression::ALL.len() {
+            return Err(anyhow::anyhow!("face model returned {} values, expected {}", output.data.len(), Expression::ALL.len()));
+        }
+        let mut values = output.data;
+        let use_filter = FACE_CONFIG.read().unwrap().functional.use_filter;
+        let settings = filter_settings(use_filter);
+        let kinds = settings.as_ref().map(|settings| settings.expressions.clone()).unwrap_or_default();
+        self.filter.apply(settings.as_ref(), &kinds, &mut values, frame.capture_time());
+        apply_expression_params(&mut values, &FACE_CONFIG.read().unwrap().params);
+        Ok(FaceBlendshapes {
+            timing: frame.timing,
+            values,
+        })
+    }
+}
+
+// 每个表情依次应用偏移、增益和响应曲线，没有配置的表情只做限幅
+fn apply_expression_params(values: &mut [f32], params: &FaceParamsConfig) {
+    for (expression, value) in Expression::ALL.iter().zip(values.iter_mut()) {
+        *value = match params.expressions.get(expression) {
+            Some(expression_params) => expression_params.apply(*value as f64) as f32,
+            None => value.clamp(0.0, 1.0),
+        };
+    }
+}
@@ -0,0 +1,188 @@
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::paper_tracker_config::config::FACE_CONFIG;
+use super::{expression::Expression, kalman_filter::{KalmanChannel, KalmanParams}, one_euro_filter::{OneEuroChannel, OneEuroParams}};
+
+// 没有时间戳间隔时按 30 帧处理
+const DEFAULT_DT: f64 = 1.0 / 30.0;
+// 两帧间隔超过这个值视为断流，重新初始化
+const MAX_DT: f64 = 1.0;
+
+// 每个输出通道单独选择的平滑方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    None,
+    #[default]
+    Kalman,
+    OneEuro,
+}
+
+// 单个通道的平滑器，第一次调用时直接返回输入值
+pub trait ChannelFilter: Send {
+    fn filter(&mut self, value: f64, dt: f64, settings: &FilterSettings) -> f64;
+}
+
+fn create_filter(kind: FilterKind) -> Option<Box<dyn ChannelFilter>> {
+    match kind {
+        FilterKind::None => None,
+        FilterKind::Kalman => Some(Box::new(KalmanChannel::default())),
+        FilterKind::OneEuro => Some(Box::new(OneEuroChannel::default())),
+    }
+}
+
+// 滤波参数在面捕配置中，面捕和眼追共用；眼追各通道的滤波方式在眼追配置中
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterSettings {
+    pub kalman: KalmanParams,
+    pub one_euro: OneEuroParams,
+    // 按 Expression::ALL 的顺序
+    pub expressions: Vec<FilterKind>,
+}
+
+// 开关由调用方从各自的配置读取，关闭时返回 None
+pub fn filter_settings(enabled: bool) -> Option<FilterSettings> {
+    if !enabled {
+        return None;
+    }
+    let face_config = FACE_CONFIG.read().unwrap();
+    let filter = &face_config.filter;
+    Some(FilterSettings {
+        kalman: KalmanParams::from_config(filter),
+        one_euro: filter.one_euro,
+        expressions: Expression::ALL.iter()
+            .map(|expression| filter.expressions.get(expression).copied().unwrap_or(filter.default_filter))
+            .collect(),
+    })
+}
+
+// 一组输出通道的平滑，每个通道可以用不同的滤波器
+#[derive(Default)]
+pub struct FilterBank {
+    channels: Vec<(FilterKind, Option<Box<dyn ChannelFilter>>)>,
+    last_timestamp: Option<Instant>,
+}
+
+impl FilterBank {
+    pub fn new() -> Self {
+        FilterBank::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.channels.clear();
+        self.last_timestamp = None;
+    }
+
+    // settings 为空表示关闭滤波，状态会被重置，再次开启时从当前值重新开始
+    pub fn apply(&mut self, settings: Option<&FilterSettings>, kinds: &[FilterKind], values: &mut [f32], timestamp: Instant) {
+        self.apply_masked(settings, kinds, values, &[], timestamp);
+    }
+
+    // skip 中为 true 的通道本帧不更新滤波状态，值保持原样，由调用方决定输出
+    pub fn apply_masked(&mut self, settings: Option<&FilterSettings>, kinds: &[FilterKind], values: &mut [f32], skip: &[bool], timestamp: Instant) {
+        let settings = match settings {
+            Some(settings) => settings,
+            None => {
+                self.reset();
+                return;
+            }
+        };
+        let measured_dt = self.last_timestamp.map(|last| timestamp.saturating_duration_since(last).as_secs_f64());
+        self.last_timestamp = Some(timestamp);
+        if measured_dt.map(|dt| dt > MAX_DT).unwrap_or(false) || self.channels.len() != values.len() {
+            self.channels.clear();
+        }
+        let dt = measured_dt.filter(|dt| *dt > 0.0).unwrap_or(DEFAULT_DT).min(MAX_DT);
+        for (index, value) in values.iter_mut().enumerate() {
+            let kind = kinds.get(index).copied().unwrap_or_default();
+            if index >= self.channels.len() {
+                self.channels.push((kind, create_filter(kind)));
+            } else if self.channels[index].0 != kind {
+                self.channels[index] = (kind, create_filter(kind));
+            }
+            if skip.get(index).copied().unwrap_or(false) {
+                continue;
+            }
+            if let Some(filter) = self.channels[index].1.as_mut() {
+                *value = filter.filter(*value as f64, dt, settings) as f32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn settings() -> FilterSettings {
+        FilterSettings {
+            kalman: KalmanParams { dt: None, q: 100.0, r: 0.01 },
+            one_euro: OneEuroParams::default(),
+            expressions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn masked_channel_is_left_untouched() {
+        let settings = settings();
+        let kinds = [FilterKind::OneEuro, FilterKind::OneEuro];
+        let start = Instant::now();
+        let mut bank = FilterBank::new();
+        bank.apply(Some(&settings), &kinds, &mut [0.0, 0.0], start);
+
+        let mut values = [1.0, 1.0];
+        bank.apply_masked(Some(&settings), &kinds, &mut values, &[true, false], start + Duration::from_millis(33));
+        assert_eq!(values[0], 1.0);
+        assert!(values[1] > 0.0 && values[1] < 1.0);
+
+        // 被跳过的通道没有看到 1.0，状态仍停在 0
+        let mut values = [0.0, 0.0];
+        bank.apply(Some(&settings), &kinds, &mut values, start + Duration::from_millis(66));
+        assert_eq!(values[0], 0.0);
+        assert!(values[1] > 0.0);
+    }
+
+    #[test]
+    fn disabling_resets_state() {
+        let settings = settings();
+        let kinds = [FilterKind::Kalman];
+        let start = Instant::now();
+        let mut bank = FilterBank::new();
+        for frame in 0..10 {
+            bank.apply(Some(&settings), &kinds, &mut [0.0], start + Duration::from_millis(33 * frame));
+        }
+        // 关闭时不修改数值
+        let mut values = [1.0];
+        bank.apply(None, &kinds, &mut values, start + Duration::from_millis(330));
+        assert_eq!(values[0], 1.0);
+        // 再次开启时从当前值重新开始，而不是从之前的 0 平滑过来
+        let mut values = [1.0];
+        bank.apply(Some(&settings), &kinds, &mut values, start + Duration::from_millis(363));
+        assert_eq!(values[0], 1.0);
+    }
+
+    #[test]
+    fn changing_kind_rebuilds_channel() {
+        let settings = settings();
+        let start = Instant::now();
+        let mut bank = FilterBank::new();
+        for frame in 0..10 {
+            bank.apply(Some(&settings), &[FilterKind::Kalman], &mut [0.0], start + Duration::from_millis(33 * frame));
+        }
+        let mut values = [1.0];
+        bank.apply(Some(&settings), &[FilterKind::Kalman], &mut values, start + Duration::from_millis(330));
+        assert!(values[0] < 1.0);
+        // 改为不滤波后原样输出
+        let mut values = [1.0];
+        bank.apply(Some(&settings), &[FilterKind::None], &mut values, start + Duration::from_millis(363));
+        assert_eq!(values[0], 1.0);
+        // 改为 One Euro 时新建滤波器，第一帧直接输出
+        let mut values = [0.0];
+        bank.apply(Some(&settings), &[FilterKind::OneEuro], &mut values, start + Duration::from_millis(396));
+        assert_eq!(values[0], 0.0);
+        let mut values = [1.0];
+        bank.apply(Some(&settings), &[FilterKind::OneEuro], &mut values, start + Duration::from_millis(429));
+        assert!(values[0] > 0.0 && values[0] < 1.0);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::paper_tracker_config::config::FaceFilterConfig;
+use super::filter::{ChannelFilter, FilterSettings};
+
+// 配置为 0 或负数时使用的默认值
+const DEFAULT_Q_FACTOR: f64 = 100.0;
+const DEFAULT_R_FACTOR: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanParams {
+    // 固定步长，为空时使用帧间实际间隔
+    pub dt: Option<f64>,
+    // 过程噪声（加速度方差），越大越跟手
+    pub q: f64,
+    // 观测噪声方差，越大越平滑
+    pub r: f64,
+}
+
+impl KalmanParams {
+    pub fn from_config(config: &FaceFilterConfig) -> Self {
+        KalmanParams {
+            dt: if config.kalman_dt > 0.0 { Some(config.kalman_dt) } else { None },
+            q: if config.kalman_q_factor > 0.0 { config.kalman_q_factor } else { DEFAULT_Q_FACTOR },
+            r: if config.r_factor > 0.0 { config.r_factor } else { DEFAULT_R_FACTOR },
+        }
+    }
+}
+
+// 单通道匀速模型，状态为 [位置, 速度]，只观测位置
+#[derive(Debug, Clone, Default)]
+pub struct KalmanChannel {
+    x: f64,
+    v: f64,
+    p: [[f64; 2]; 2],
+    initialized: bool,
+}
+
+impl ChannelFilter for KalmanChannel {
+    fn filter(&mut self, z: f64, dt: f64, settings: &FilterSettings) -> f64 {
+        let params = &settings.kalman;
+        if !self.initialized {
+            self.x = z;
+            self.v = 0.0;
+            self.p = [[params.r, 0.0], [0.0, 1.0]];
+            self.initialized = true;
+            return z;
+        }
+        let dt = params.dt.unwrap_or(dt);
+        // 预测：x = F x, P = F P F^T + Q
+        self.x += self.v * dt;
+        let [[p00, p01], [p10, p11]] = self.p;
+        let dt2 = dt * dt;
+        let q = params.q;
+        let p00 = p00 + dt * (p10 + p01) + dt2 * p11 + q * dt2 * dt2 / 4.0;
+        let p01 = p01 + dt * p11 + q * dt2 * dt / 2.0;
+        let p10 = p10 + dt * p11 + q * dt2 * dt / 2.0;
+        let p11 = p11 + q * dt2;
+
+        // 更新：H = [1, 0]
+        let s = p00 + params.r;
+        let k0 = p00 / s;
+        let k1 = p10 / s;
+        let innovation = z - self.x;
+        self.x += k0 * innovation;
+        self.v += k1 * innovation;
+        self.p = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+        self.x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{filter::FilterKind, one_euro_filter::OneEuroParams};
+
+    const DT: f64 = 1.0 / 30.0;
+
+    fn settings() -> FilterSettings {
+        FilterSettings {
+            kalman: KalmanParams { dt: None, q: DEFAULT_Q_FACTOR, r: DEFAULT_R_FACTOR },
+            one_euro: OneEuroParams::default(),
+            expressions: Vec::new(),
+        }
+    }
+
+    // 固定种子的均匀噪声，幅度 ±0.1
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 0.2
+        }
+    }
+
+    fn variance(values: &[f64]) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64
+    }
+
+    #[test]
+    fn first_value_passes_through() {
+        let mut channel = KalmanChannel::default();
+        assert_eq!(channel.filter(0.7, DT, &settings()), 0.7);
+    }
+
+    #[test]
+    fn noisy_step_is_smoothed_with_bounded_lag() {
+        let settings = settings();
+        let mut channel = KalmanChannel::default();
+        let mut noise = Noise(12345);
+        let (mut raw, mut filtered) = (Vec::new(), Vec::new());
+        for frame in 0..120 {
+            let target = if frame >= 30 { 1.0 } else { 0.0 };
+            let value = target + noise.next();
+            raw.push(value);
+            filtered.push(channel.filter(value, DT, &settings));
+        }
+        // 稳定后的抖动明显小于原始输入
+        assert!(variance(&filtered[90..]) < variance(&raw[90..]) * 0.5);
+        // 阶跃后 5 帧内跟上，且过冲有限
+        assert!(filtered[35] > 0.9, "{}", filtered[35]);
+        assert!(filtered[30..].iter().all(|value| *value < 1.3));
+    }
+
+    #[test]
+    fn noisy_ramp_is_tracked_without_steady_lag() {
+        let settings = settings();
+        let mut channel = KalmanChannel::default();
+        let mut noise = Noise(12345);
+        let mut errors = Vec::new();
+        for frame in 0..150 {
+            let target = 0.5 * frame as f64 * DT;
+            errors.push(channel.filter(target + noise.next(), DT, &settings) - target);
+        }
+        // 匀速模型跟踪斜坡时没有稳态滞后
+        let mean_error = errors[120..].iter().sum::<f64>() / 30.0;
+        assert!(mean_error.abs() < 0.02, "{}", mean_error);
+    }
+
+    #[test]
+    fn zero_config_uses_defaults() {
+        let config = FaceFilterConfig {
+            kalman_dt: 0.0,
+            kalman_q_factor: 0.0,
+            r_factor: 0.0,
+            one_euro: OneEuroParams::default(),
+            default_filter: FilterKind::Kalman,
+            expressions: Default::default(),
+        };
+        assert_eq!(KalmanParams::from_config(&config), KalmanParams { dt: None, q: DEFAULT_Q_FACTOR, r: DEFAULT_R_FACTOR });
+    }
+}
@@ -1 +1,10 @@
-pub mod onnxrt_inference;
\ No newline at end of file
+pub mod onnxrt_inference;
+pub mod face_tracker;
+pub mod eye_tracker;
+pub mod pupil_detector;
+pub mod expression;
+pub mod kalman_filter;
+pub mod one_euro_filter;
+pub mod filter;
+pub mod response_curve;
+pub mod eye_calibration;
\ No newline at end of file
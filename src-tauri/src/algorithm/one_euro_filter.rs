@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use super::filter::{ChannelFilter, FilterSettings};
+
+// One Euro 滤波参数：静止时按 min_cutoff 强平滑，变化越快截止频率越高，眨眼、张嘴等快速动作延迟更小
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct OneEuroParams {
+    // 最低截止频率（Hz），越小静止时越稳
+    #[serde(default = "default_min_cutoff")]
+    pub min_cutoff: f64,
+    // 截止频率随速度增加的系数，越大快速动作越跟手
+    #[serde(default = "default_beta")]
+    pub beta: f64,
+    // 速度估计的截止频率（Hz）
+    #[serde(default = "default_d_cutoff")]
+    pub d_cutoff: f64,
+}
+
+fn default_min_cutoff() -> f64 {
+    1.0
+}
+
+fn default_beta() -> f64 {
+    0.5
+}
+
+fn default_d_cutoff() -> f64 {
+    1.0
+}
+
+impl Default for OneEuroParams {
+    fn default() -> Self {
+        OneEuroParams {
+            min_cutoff: default_min_cutoff(),
+            beta: default_beta(),
+            d_cutoff: default_d_cutoff(),
+        }
+    }
+}
+
+fn smoothing_factor(cutoff: f64, dt: f64) -> f64 {
+    let tau = 1.0 / (2.0 * std::f64::consts::PI * cutoff.max(1e-6));
+    1.0 / (1.0 + tau / dt)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OneEuroChannel {
+    x: f64,
+    dx: f64,
+    initialized: bool,
+}
+
+impl ChannelFilter for OneEuroChannel {
+    fn filter(&mut self, value: f64, dt: f64, settings: &FilterSettings) -> f64 {
+        let params = &settings.one_euro;
+        if !self.initialized || dt <= 0.0 {
+            self.x = value;
+            self.dx = 0.0;
+            self.initialized = true;
+            return value;
+        }
+        let alpha_d = smoothing_factor(params.d_cutoff, dt);
+        self.dx += alpha_d * ((value - self.x) / dt - self.dx);
+        let cutoff = params.min_cutoff + params.beta * self.dx.abs();
+        let alpha = smoothing_factor(cutoff, dt);
+        self.x += alpha * (value - self.x);
+        self.x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::kalman_filter::KalmanParams;
+
+    const DT: f64 = 1.0 / 30.0;
+
+    fn settings(beta: f64) -> FilterSettings {
+        FilterSettings {
+            kalman: KalmanParams { dt: None, q: 100.0, r: 0.01 },
+            one_euro: OneEuroParams { min_cutoff: 1.0, beta, d_cutoff: 1.0 },
+            expressions: Vec::new(),
+        }
+    }
+
+    // 以 1/s 的速度匀速变化 3 秒后的滞后量
+    fn ramp_lag(beta: f64) -> f64 {
+        let settings = settings(beta);
+        let mut channel = OneEuroChannel::default();
+        let mut lag = 0.0;
+        for frame in 0..90 {
+            let target = frame as f64 * DT;
+            lag = target - channel.filter(target, DT, &settings);
+        }
+        lag
+    }
+
+    #[test]
+    fn first_value_and_zero_dt_pass_through() {
+        let settings = settings(0.5);
+        let mut channel = OneEuroChannel::default();
+        assert_eq!(channel.filter(0.3, DT, &settings), 0.3);
+        assert!(channel.filter(0.6, DT, &settings) < 0.6);
+        assert_eq!(channel.filter(0.9, 0.0, &settings), 0.9);
+    }
+
+    #[test]
+    fn stationary_noise_is_smoothed() {
+        let settings = settings(0.5);
+        let mut channel = OneEuroChannel::default();
+        let mut seed: u64 = 12345;
+        let (mut raw, mut filtered) = (Vec::new(), Vec::new());
+        for _ in 0..120 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let value = 0.5 + ((seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 0.2;
+            raw.push(value);
+            filtered.push(channel.filter(value, DT, &settings));
+        }
+        let variance = |values: &[f64]| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64
+        };
+        assert!(variance(&filtered[30..]) < variance(&raw[30..]) * 0.25);
+    }
+
+    #[test]
+    fn beta_reduces_lag_on_fast_motion() {
+        // beta 为 0 时是截止频率 min_cutoff 的一阶低通，斜坡滞后为 速度 / (2π·min_cutoff)
+        let fixed = ramp_lag(0.0);
+        assert!((fixed - 1.0 / (2.0 * std::f64::consts::PI)).abs() < 0.01, "{}", fixed);
+        let adaptive = ramp_lag(0.5);
+        let fast = ramp_lag(5.0);
+        assert!(adaptive < fixed * 0.5, "{} {}", adaptive, fixed);
+        assert!(fast < adaptive, "{} {}", fast, adaptive);
+    }
+
+    #[test]
+    fn smoothing_factor_grows_with_cutoff() {
+        assert!(smoothing_factor(1.0, DT) < smoothing_factor(10.0, DT));
+        assert!(smoothing_factor(1000.0, DT) > 0.99);
+        assert!(smoothing_factor(0.0, DT) < 1e-6);
+    }
+}
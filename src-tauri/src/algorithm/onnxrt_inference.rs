@@ -1,13 +1,221 @@
-// use onnxruntime;
+use std::{path::{Path, PathBuf}, time::Duration};
+use once_cell::sync::Lazy;
+use opencv::{core::{Mat, MatTraitConst, Rect, Size}, imgproc};
+use onnxruntime::{environment::Environment, ndarray::{ArrayD, IxDyn}, session::Session, tensor::OrtOwnedTensor, GraphOptimizationLevel, LoggingLevel, TensorElementDataType};
+use tauri::{AppHandle, Manager, Runtime};
+use ftlog::*;
+use crate::websocket::preprocess::to_gray;
 
+// 模型与其他资源一起放在安装目录的 assets 下
+const MODEL_DIR: &str = "assets";
+// 模型加载失败后的重试间隔，放入模型后无需重启
+pub(crate) const MODEL_RETRY_INTERVAL: Duration = Duration::from_secs(5);
 
-// pub struct Inference {
+// onnxruntime 只允许存在一个 Environment，所有会话共用
+static ENVIRONMENT: Lazy<Result<Environment, String>> = Lazy::new(|| {
+    Environment::builder()
+        .with_name("papertracker")
+        .with_log_level(LoggingLevel::Warning)
+        .build()
+        .map_err(|e| e.to_string())
+});
 
-// }
+pub fn resolve_model_path<R: Runtime>(app: &AppHandle<R>, model_name: &str) -> anyhow::Result<PathBuf> {
+    let path = app.path()
+        .resolve(format!("{}/{}", MODEL_DIR, model_name), tauri::path::BaseDirectory::Resource)
+        .map_err(|e| anyhow::anyhow!("模型路径解析失败 {}: {}", model_name, e))?;
+    if !path.exists() {
+        return Err(anyhow::anyhow!("模型文件不存在: {}", path.display()));
+    }
+    Ok(path)
+}
 
+// 裁剪 ROI、转灰度并缩放到模型输入大小，像素归一化到 [0, 1]，按 NCHW 排列
+pub fn gray_tensor(image: &Mat, rect: Rect, width: i32, height: i32) -> anyhow::Result<ArrayD<f32>> {
+    let cropped = Mat::roi(image, rect)?.try_clone()?;
+    let gray = to_gray(&cropped)?;
+    let mut resized = Mat::default();
+    imgproc::resize(&gray, &mut resized, Size::new(width, height), 0.0, 0.0, imgproc::INTER_AREA)?;
+    let data = resized.data_bytes()?.iter().map(|pixel| *pixel as f32 / 255.0).collect();
+    Ok(ArrayD::from_shape_vec(IxDyn(&[1, 1, height as usize, width as usize]), data)?)
+}
 
-// impl Inference {
-//     pub fn new(model_path: &str) -> Self { 
+// 张量形状，None 表示动态维度（如 batch）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorShape {
+    pub name: String,
+    pub dims: Vec<Option<usize>>,
+}
 
-//     }
-// }
\ No newline at end of file
+impl TensorShape {
+    pub fn matches(&self, shape: &[usize]) -> bool {
+        self.dims.len() == shape.len()
+            && self.dims.iter().zip(shape).all(|(expected, actual)| expected.map(|dim| dim == *actual).unwrap_or(true))
+    }
+
+    // 模型声明的维度与调用方期望的维度是否兼容，动态维度视为兼容
+    fn compatible(&self, expected: &[Option<usize>]) -> bool {
+        self.dims.len() == expected.len()
+            && self.dims.iter().zip(expected).all(|(dim, expected)| match (dim, expected) {
+                (Some(dim), Some(expected)) => dim == expected,
+                _ => true,
+            })
+    }
+}
+
+// 一个输出张量，按行优先展开
+#[derive(Debug, Clone)]
+pub struct InferenceOutput {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+// CPU 上运行的单输入 float 模型
+pub struct Inference {
+    model_path: PathBuf,
+    session: Session<'static>,
+    input: TensorShape,
+    outputs: Vec<TensorShape>,
+}
+
+impl Inference {
+    pub fn new(model_path: &Path, threads: usize) -> anyhow::Result<Self> {
+        let environment = ENVIRONMENT.as_ref().map_err(|e| anyhow::anyhow!("onnxruntime 初始化失败: {}", e))?;
+        let session = environment
+            .new_session_builder()?
+            .with_optimization_level(GraphOptimizationLevel::Basic)?
+            .with_number_threads(threads.clamp(1, i16::MAX as usize) as i16)?
+            .with_model_from_file(model_path)?;
+
+        if session.inputs.len() != 1 {
+            return Err(anyhow::anyhow!("模型 {} 需要 1 个输入，实际为 {}", model_path.display(), session.inputs.len()));
+        }
+        let input = &session.inputs[0];
+        if input.input_type != TensorElementDataType::Float {
+            return Err(anyhow::anyhow!("模型 {} 输入类型不是 float: {:?}", model_path.display(), input.input_type));
+        }
+        let input = TensorShape {
+            name: input.name.clone(),
+            dims: input.dimensions.iter().map(|dim| dim.map(|dim| dim as usize)).collect(),
+        };
+        let mut outputs = Vec::new();
+        for output in session.outputs.iter() {
+            if output.output_type != TensorElementDataType::Float {
+                return Err(anyhow::anyhow!("模型 {} 输出 {} 类型不是 float: {:?}", model_path.display(), output.name, output.output_type));
+            }
+            outputs.push(TensorShape {
+                name: output.name.clone(),
+                dims: output.dimensions.iter().map(|dim| dim.map(|dim| dim as usize)).collect(),
+            });
+        }
+        info!("Loaded model {} with {} threads, input {:?}, outputs {:?}", model_path.display(), threads, input, outputs);
+        Ok(Inference {
+            model_path: model_path.to_path_buf(),
+            session,
+            input,
+            outputs,
+        })
+    }
+
+    pub fn input_shape(&self) -> &TensorShape {
+        &self.input
+    }
+
+    pub fn output_shapes(&self) -> &[TensorShape] {
+        &self.outputs
+    }
+
+    // 加载后由调用方校验模型是否符合预期，避免换错模型时推理出错误结果
+    pub fn validate(&self, input: &[Option<usize>], outputs: &[&[Option<usize>]]) -> anyhow::Result<()> {
+        if !self.input.compatible(input) {
+            return Err(anyhow::anyhow!("模型 {} 输入形状 {:?} 与期望 {:?} 不符", self.model_path.display(), self.input.dims, input));
+        }
+        if self.outputs.len() != outputs.len() {
+            return Err(anyhow::anyhow!("模型 {} 输出数量 {} 与期望 {} 不符", self.model_path.display(), self.outputs.len(), outputs.len()));
+        }
+        for (output, expected) in self.outputs.iter().zip(outputs) {
+            if !output.compatible(expected) {
+                return Err(anyhow::anyhow!("模型 {} 输出 {} 形状 {:?} 与期望 {:?} 不符", self.model_path.display(), output.name, output.dims, expected));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self, input: ArrayD<f32>) -> anyhow::Result<Vec<InferenceOutput>> {
+        if !self.input.matches(input.shape()) {
+            return Err(anyhow::anyhow!("输入形状 {:?} 与模型 {:?} 不符", input.shape(), self.input.dims));
+        }
+        let results: Vec<OrtOwnedTensor<f32, IxDyn>> = self.session.run(vec![input])?;
+        Ok(results
+            .iter()
+            .zip(self.outputs.iter())
+            .map(|(tensor, shape)| InferenceOutput {
+                name: shape.name.clone(),
+                shape: tensor.shape().to_vec(),
+                data: tensor.iter().copied().collect(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // y = x + 1，x 形状 [N, 3]，由 test-fixtures/make_add_one.py 生成
+    fn add_one() -> Inference {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-fixtures/add_one.onnx");
+        Inference::new(&path, 1).unwrap()
+    }
+
+    #[test]
+    fn loads_declared_shapes() {
+        let model = add_one();
+        assert_eq!(model.input_shape(), &TensorShape { name: "x".to_string(), dims: vec![None, Some(3)] });
+        assert_eq!(model.output_shapes(), &[TensorShape { name: "y".to_string(), dims: vec![None, Some(3)] }]);
+    }
+
+    #[test]
+    fn missing_model_fails_to_load() {
+        assert!(Inference::new(Path::new("test-fixtures/missing.onnx"), 1).is_err());
+    }
+
+    #[test]
+    fn validate_treats_dynamic_dims_as_compatible() {
+        let model = add_one();
+        assert!(model.validate(&[Some(1), Some(3)], &[&[None, Some(3)]]).is_ok());
+        assert!(model.validate(&[None, None], &[&[Some(8), None]]).is_ok());
+        assert!(model.validate(&[Some(1), Some(4)], &[&[None, Some(3)]]).is_err());
+        assert!(model.validate(&[Some(1), Some(3), Some(1)], &[&[None, Some(3)]]).is_err());
+        assert!(model.validate(&[None, Some(3)], &[&[None, Some(4)]]).is_err());
+        assert!(model.validate(&[None, Some(3)], &[]).is_err());
+    }
+
+    #[test]
+    fn matches_checks_rank_and_fixed_dims() {
+        let shape = TensorShape { name: "x".to_string(), dims: vec![None, Some(3)] };
+        assert!(shape.matches(&[1, 3]));
+        assert!(shape.matches(&[16, 3]));
+        assert!(!shape.matches(&[1, 4]));
+        assert!(!shape.matches(&[3]));
+    }
+
+    #[test]
+    fn run_round_trip() {
+        let mut model = add_one();
+        let input = ArrayD::from_shape_vec(IxDyn(&[2, 3]), vec![0.0, 1.0, 2.0, -1.0, 0.5, 10.0]).unwrap();
+        let outputs = model.run(input).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].name, "y");
+        assert_eq!(outputs[0].shape, vec![2, 3]);
+        assert_eq!(outputs[0].data, vec![1.0, 2.0, 3.0, 0.0, 1.5, 11.0]);
+    }
+
+    #[test]
+    fn run_rejects_wrong_input_shape() {
+        let mut model = add_one();
+        assert!(model.run(ArrayD::zeros(IxDyn(&[2, 4]))).is_err());
+        assert!(model.run(ArrayD::zeros(IxDyn(&[3]))).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use opencv::{core::{self, Mat, MatTraitConst, Point, Size, Vector}, imgproc};
+use serde::{Deserialize, Serialize};
+use crate::websocket::preprocess::to_gray;
+use super::eye_tracker::{EyeDetector, EyeObservation};
+
+// 传统方法的瞳孔检测参数，适用于红外补光下的暗瞳图像
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PupilDetectorConfig {
+    // 二值化阈值 = 图像最暗值 + 该偏移
+    #[serde(default = "default_threshold_offset")]
+    pub threshold_offset: f64,
+    // 瞳孔面积占图像面积的范围
+    #[serde(default = "default_min_area_ratio")]
+    pub min_area_ratio: f64,
+    #[serde(default = "default_max_area_ratio")]
+    pub max_area_ratio: f64,
+    // 去掉贴着补光反光点的轮廓点后再拟合椭圆
+    #[serde(default = "default_reject_glints")]
+    pub reject_glints: bool,
+    #[serde(default = "default_glint_threshold")]
+    pub glint_threshold: f64,
+}
+
+fn default_threshold_offset() -> f64 {
+    20.0
+}
+
+fn default_min_area_ratio() -> f64 {
+    0.002
+}
+
+fn default_max_area_ratio() -> f64 {
+    0.25
+}
+
+fn default_reject_glints() -> bool {
+    true
+}
+
+fn default_glint_threshold() -> f64 {
+    230.0
+}
+
+impl Default for PupilDetectorConfig {
+    fn default() -> Self {
+        PupilDetectorConfig {
+            threshold_offset: default_threshold_offset(),
+            min_area_ratio: default_min_area_ratio(),
+            max_area_ratio: default_max_area_ratio(),
+            reject_glints: default_reject_glints(),
+            glint_threshold: default_glint_threshold(),
+        }
+    }
+}
+
+// 瞳孔椭圆拟合结果，坐标为输入图像的像素坐标
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PupilFit {
+    pub center_x: f32,
+    pub center_y: f32,
+    // 长轴和短轴的全长
+    pub major_axis: f32,
+    pub minor_axis: f32,
+    pub angle: f32,
+    // 0 到 1，综合轮廓圆度和轮廓与椭圆的吻合程度
+    pub quality: f32,
+    // 可见轮廓面积与拟合椭圆面积之比，眼睑遮挡时变小
+    pub visible_ratio: f32,
+}
+
+pub struct PupilDetector {
+    config: PupilDetectorConfig,
+}
+
+impl PupilDetector {
+    pub fn new(config: PupilDetectorConfig) -> Self {
+        PupilDetector { config }
+    }
+
+    pub fn detect_pupil(&self, image: &Mat) -> opencv::Result<Option<PupilFit>> {
+        let gray = to_gray(image)?;
+        let mut blurred = Mat::default();
+        imgproc::gaussian_blur_def(&gray, &mut blurred, Size::new(5, 5), 0.0)?;
+
+        // 暗瞳：最暗区域附近的像素为候选
+        let mut min_value = 0.0;
+        core::min_max_loc(&blurred, Some(&mut min_value), None, None, None, &core::no_array())?;
+        let mut binary = Mat::default();
+        imgproc::threshold(&blurred, &mut binary, min_value + self.config.threshold_offset, 255.0, imgproc::THRESH_BINARY_INV)?;
+        // 开运算去掉睫毛等细小暗区
+        let kernel = imgproc::get_structuring_element_def(imgproc::MORPH_ELLIPSE, Size::new(5, 5))?;
+        let mut opened = Mat::default();
+        imgproc::morphology_ex_def(&binary, &mut opened, imgproc::MORPH_OPEN, &kernel)?;
+
+        let mut contours = Vector::<Vector<Point>>::new();
+        imgproc::find_contours_def(&opened, &mut contours, imgproc::RETR_EXTERNAL, imgproc::CHAIN_APPROX_NONE)?;
+
+        let image_area = (image.cols() * image.rows()) as f64;
+        let min_area = image_area * self.config.min_area_ratio;
+        let max_area = image_area * self.config.max_area_ratio;
+        let mut best: Option<(f64, Vector<Point>, f64, f64)> = None;
+        for contour in contours.iter() {
+            let area = imgproc::contour_area_def(&contour)?;
+            if area < min_area || area > max_area {
+                continue;
+            }
+            let perimeter = imgproc::arc_length(&contour, true)?;
+            if perimeter <= 0.0 {
+                continue;
+            }
+            let circularity = (4.0 * std::f64::consts::PI * area / (perimeter * perimeter)).min(1.0);
+            // 圆度优先，面积用来区分同样圆的小噪点
+            let score = circularity * area.sqrt();
+            if best.as_ref().map(|(best_score, _, _, _)| score > *best_score).unwrap_or(true) {
+                best = Some((score, contour, area, circularity));
+            }
+        }
+        let (_, contour, area, circularity) = match best {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+
+        let points = if self.config.reject_glints {
+            self.reject_glint_points(&gray, &contour)?
+        } else {
+            contour
+        };
+        if points.len() < 5 {
+            return Ok(None);
+        }
+        let ellipse = imgproc::fit_ellipse(&points)?;
+        let major_axis = ellipse.size.width.max(ellipse.size.height);
+        let minor_axis = ellipse.size.width.min(ellipse.size.height);
+        let ellipse_area = std::f64::consts::PI * major_axis as f64 * minor_axis as f64 / 4.0;
+        if ellipse_area <= 0.0 {
+            return Ok(None);
+        }
+        let visible_ratio = (area / ellipse_area).min(1.0);
+        Ok(Some(PupilFit {
+            center_x: ellipse.center.x,
+            center_y: ellipse.center.y,
+            major_axis,
+            minor_axis,
+            angle: ellipse.angle,
+            quality: (circularity * visible_ratio).clamp(0.0, 1.0) as f32,
+            visible_ratio: visible_ratio as f32,
+        }))
+    }
+
+    // 反光点会在瞳孔边缘留下缺口，去掉靠近反光点的轮廓点
+    fn reject_glint_points(&self, gray: &Mat, contour: &Vector<Point>) -> opencv::Result<Vector<Point>> {
+        let mut glints = Mat::default();
+        imgproc::threshold(gray, &mut glints, self.config.glint_threshold, 255.0, imgproc::THRESH_BINARY)?;
+        let kernel = imgproc::get_structuring_element_def(imgproc::MORPH_ELLIPSE, Size::new(7, 7))?;
+        let mut glint_mask = Mat::default();
+        imgproc::dilate_def(&glints, &mut glint_mask, &kernel)?;
+        let mut points = Vector::<Point>::new();
+        for point in contour.iter() {
+            if *glint_mask.at_2d::<u8>(point.y, point.x)? == 0 {
+                points.push(point);
+            }
+        }
+        Ok(points)
+    }
+}
+
+impl EyeDetector for PupilDetector {
+    fn name(&self) -> &str {
+        "pupil"
+    }
+
+    fn detect(&mut self, image: &Mat) -> anyhow::Result<EyeObservation> {
+        let fit = match self.detect_pupil(image)? {
+            Some(fit) => fit,
+            // 找不到瞳孔通常是闭眼
+            None => return Ok(EyeObservation::default()),
+        };
+        let width = image.cols().max(1) as f32;
+        let height = image.rows().max(1) as f32;
+        Ok(EyeObservation {
+            gaze_x: (fit.center_x / width * 2.0 - 1.0).clamp(-1.0, 1.0),
+            gaze_y: (fit.center_y / height * 2.0 - 1.0).clamp(-1.0, 1.0),
+            // 没有模型时用瞳孔可见比例近似睁眼程度
+            openness: fit.visible_ratio,
+            confidence: fit.quality,
+            pupil: Some(fit),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::{Scalar, CV_8UC1};
+
+    const CENTER: (f32, f32) = (80.0, 60.0);
+    // 半轴长度，全长为 40 x 28
+    const AXES: (i32, i32) = (20, 14);
+
+    // 灰色背景上的暗色椭圆瞳孔，可选在瞳孔右边缘放一个反光点
+    fn eye_image(glint: bool) -> Mat {
+        let mut image = Mat::new_rows_cols_with_default(120, 160, CV_8UC1, Scalar::all(150.0)).unwrap();
+        imgproc::ellipse(
+            &mut image,
+            Point::new(CENTER.0 as i32, CENTER.1 as i32),
+            Size::new(AXES.0, AXES.1),
+            0.0, 0.0, 360.0,
+            Scalar::all(20.0),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        ).unwrap();
+        if glint {
+            imgproc::circle(&mut image, Point::new(CENTER.0 as i32 + AXES.0 - 2, CENTER.1 as i32), 4, Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+        }
+        image
+    }
+
+    fn assert_fit(fit: &PupilFit) {
+        assert!((fit.center_x - CENTER.0).abs() < 1.5, "center_x {}", fit.center_x);
+        assert!((fit.center_y - CENTER.1).abs() < 1.5, "center_y {}", fit.center_y);
+        // 模糊后阈值落在边缘内侧，轴长略小于绘制值
+        assert!((fit.major_axis - 2.0 * AXES.0 as f32).abs() < 4.0, "major_axis {}", fit.major_axis);
+        assert!((fit.minor_axis - 2.0 * AXES.1 as f32).abs() < 4.0, "minor_axis {}", fit.minor_axis);
+    }
+
+    #[test]
+    fn fits_dark_ellipse() {
+        let fit = PupilDetector::new(PupilDetectorConfig::default()).detect_pupil(&eye_image(false)).unwrap().unwrap();
+        assert_fit(&fit);
+        assert!(fit.quality > 0.8, "quality {}", fit.quality);
+        assert!(fit.visible_ratio > 0.8, "visible_ratio {}", fit.visible_ratio);
+    }
+
+    #[test]
+    fn fits_ellipse_with_glint() {
+        let fit = PupilDetector::new(PupilDetectorConfig::default()).detect_pupil(&eye_image(true)).unwrap().unwrap();
+        assert_fit(&fit);
+    }
+
+    #[test]
+    fn uniform_image_has_no_pupil() {
+        let image = Mat::new_rows_cols_with_default(120, 160, CV_8UC1, Scalar::all(150.0)).unwrap();
+        assert!(PupilDetector::new(PupilDetectorConfig::default()).detect_pupil(&image).unwrap().is_none());
+    }
+
+    #[test]
+    fn detect_centers_gaze() {
+        let observation = PupilDetector::new(PupilDetectorConfig::default()).detect(&eye_image(false)).unwrap();
+        assert!(observation.gaze_x.abs() < 0.05 && observation.gaze_y.abs() < 0.05);
+        assert!(observation.confidence > 0.8);
+        assert!(observation.pupil.is_some());
+    }
+}
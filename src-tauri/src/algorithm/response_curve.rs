@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+// 单个表情在增益之后的响应曲线，输入输出都在 [0, 1]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    // 低于 deadzone 输出 0，高于 saturation 输出 1，中间按幂函数映射
+    Power {
+        #[serde(default = "default_exponent")]
+        exponent: f64,
+        #[serde(default)]
+        deadzone: f64,
+        #[serde(default = "default_saturation")]
+        saturation: f64,
+    },
+    // 控制点 [输入, 输出]，按输入升序，点之间线性插值
+    Piecewise {
+        points: Vec<[f64; 2]>,
+    },
+}
+
+fn default_exponent() -> f64 {
+    1.0
+}
+
+fn default_saturation() -> f64 {
+    1.0
+}
+
+impl ResponseCurve {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ResponseCurve::Linear => Ok(()),
+            ResponseCurve::Power { exponent, deadzone, saturation } => {
+                if !exponent.is_finite() || *exponent <= 0.0 {
+                    return Err(format!("无效的指数: {}", exponent));
+                }
+                if !(0.0..1.0).contains(deadzone) || *saturation <= *deadzone || *saturation > 1.0 {
+                    return Err(format!("无效的死区/饱和点: {} / {}", deadzone, saturation));
+                }
+                Ok(())
+            }
+            ResponseCurve::Piecewise { points } => {
+                if points.len() < 2 {
+                    return Err("控制点至少需要两个".to_string());
+                }
+                if points.iter().flatten().any(|v| !(0.0..=1.0).contains(v)) {
+                    return Err("控制点必须在 [0, 1] 范围内".to_string());
+                }
+                if points.windows(2).any(|pair| pair[1][0] <= pair[0][0]) {
+                    return Err("控制点的输入必须严格递增".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // 输出限制在 [0, 1]，即使曲线未经校验（如手动编辑的配置）也不会越界
+    pub fn apply(&self, value: f64) -> f64 {
+        let value = value.clamp(0.0, 1.0);
+        let output = match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Power { exponent, deadzone, saturation } => {
+                if value <= *deadzone {
+                    0.0
+                } else if value >= *saturation {
+                    1.0
+                } else {
+                    ((value - deadzone) / (saturation - deadzone)).powf(*exponent)
+                }
+            }
+            ResponseCurve::Piecewise { points } => {
+                let (first, last) = match (points.first(), points.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return value,
+                };
+                if value <= first[0] {
+                    return first[1];
+                }
+                if value >= last[0] {
+                    return last[1];
+                }
+                for pair in points.windows(2) {
+                    let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+                    if value <= x1 {
+                        if x1 <= x0 {
+                            return y1;
+                        }
+                        return y0 + (y1 - y0) * (value - x0) / (x1 - x0);
+                    }
+                }
+                last[1]
+            }
+        };
+        output.clamp(0.0, 1.0)
+    }
+
+    // 在 [0, 1] 上均匀采样，供界面绘制曲线
+    pub fn sample(&self, count: usize) -> Vec<[f64; 2]> {
+        sample_unit(count, |x| self.apply(x))
+    }
+}
+
+pub fn sample_unit(count: usize, f: impl Fn(f64) -> f64) -> Vec<[f64; 2]> {
+    let count = count.max(2);
+    (0..count)
+        .map(|i| {
+            let x = i as f64 / (count - 1) as f64;
+            [x, f(x)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(exponent: f64, deadzone: f64, saturation: f64) -> ResponseCurve {
+        ResponseCurve::Power { exponent, deadzone, saturation }
+    }
+
+    fn piecewise(points: &[[f64; 2]]) -> ResponseCurve {
+        ResponseCurve::Piecewise { points: points.to_vec() }
+    }
+
+    #[test]
+    fn linear_clamps_input() {
+        assert_eq!(ResponseCurve::Linear.apply(0.3), 0.3);
+        assert_eq!(ResponseCurve::Linear.apply(-1.0), 0.0);
+        assert_eq!(ResponseCurve::Linear.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn power_deadzone_and_saturation_edges() {
+        let curve = power(2.0, 0.2, 0.8);
+        assert_eq!(curve.apply(0.0), 0.0);
+        // 死区和饱和点本身分别输出 0 和 1
+        assert_eq!(curve.apply(0.2), 0.0);
+        assert!(curve.apply(0.2 + 1e-9) < 1e-12);
+        assert_eq!(curve.apply(0.8), 1.0);
+        assert!(curve.apply(0.8 - 1e-9) > 1.0 - 1e-6);
+        assert_eq!(curve.apply(1.0), 1.0);
+        // 中点 (0.5 - 0.2) / 0.6 = 0.5，平方为 0.25
+        assert!((curve.apply(0.5) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn power_exponent_shapes_response() {
+        let soft = power(0.5, 0.0, 1.0);
+        let hard = power(2.0, 0.0, 1.0);
+        assert!(soft.apply(0.25) > 0.25 && hard.apply(0.25) < 0.25);
+        assert!((soft.apply(0.25) - 0.5).abs() < 1e-12);
+        assert!((hard.apply(0.5) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn piecewise_interpolates_between_points() {
+        let curve = piecewise(&[[0.2, 0.1], [0.5, 0.8], [1.0, 0.9]]);
+        // 第一个点之前和最后一个点之后保持端点的输出
+        assert_eq!(curve.apply(0.0), 0.1);
+        assert_eq!(curve.apply(0.2), 0.1);
+        assert_eq!(curve.apply(1.0), 0.9);
+        // 落在控制点上和两点之间
+        assert!((curve.apply(0.5) - 0.8).abs() < 1e-12);
+        assert!((curve.apply(0.35) - 0.45).abs() < 1e-12);
+        assert!((curve.apply(0.75) - 0.85).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unvalidated_curves_stay_in_range() {
+        assert_eq!(piecewise(&[[0.0, -0.5], [1.0, 1.5]]).apply(0.0), 0.0);
+        assert_eq!(piecewise(&[[0.0, -0.5], [1.0, 1.5]]).apply(1.0), 1.0);
+        assert_eq!(piecewise(&[]).apply(0.4), 0.4);
+        assert_eq!(power(1.0, 0.5, 0.2).apply(0.4), 0.0);
+    }
+
+    #[test]
+    fn validate_rejects_invalid_curves() {
+        assert!(ResponseCurve::Linear.validate().is_ok());
+        assert!(power(2.0, 0.1, 0.9).validate().is_ok());
+        assert!(power(0.0, 0.0, 1.0).validate().is_err());
+        assert!(power(f64::NAN, 0.0, 1.0).validate().is_err());
+        assert!(power(1.0, 0.5, 0.5).validate().is_err());
+        assert!(power(1.0, 0.0, 1.5).validate().is_err());
+        assert!(piecewise(&[[0.0, 0.0], [1.0, 1.0]]).validate().is_ok());
+        assert!(piecewise(&[[0.0, 0.0]]).validate().is_err());
+        assert!(piecewise(&[[0.5, 0.0], [0.5, 1.0]]).validate().is_err());
+        assert!(piecewise(&[[0.0, 0.0], [1.0, 1.2]]).validate().is_err());
+    }
+}
@@ -1,17 +1,33 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{atomic::{AtomicBool, AtomicI32, AtomicU64}, Arc, Mutex};
 use bus::BusReader;
 use tauri::{AppHandle, Manager, Runtime};
 use crate::paper_tracker_config::config::{FACE_CONFIG, EYE_CONFIG};
+use crate::websocket::{frame_hub::FrameHub, reconnect::ConnectionStats, frame_source::create_frame_source, latency::LatencyStats, image_msg::{StatusCell, StreamSettingRequest}, stereo_sync::{StereoFrame, StereoStats, StereoSynchronizer}};
 use crate::utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE};
 use crossbeam::channel::{Sender, Receiver};
+use crate::algorithm::{eye_calibration::EyeCalibrationSession, eye_tracker::{EyeFrameInput, EyeState, EyeTracker}, face_tracker::{FaceBlendshapes, FaceTracker}};
+use super::preview::PreviewState;
 
 pub struct ImageStreamState {
-    pub face_stream_resp: Arc::<Mutex<bus::BusReader<crate::websocket::image_msg::ImageResponse>>>,
-    pub left_eye_stream_resp: Arc::<Mutex<bus::BusReader<crate::websocket::image_msg::ImageResponse>>>,
-    pub right_eye_stream_resp: Arc::<Mutex<bus::BusReader<crate::websocket::image_msg::ImageResponse>>>,
-    pub face_stream_req: Sender<crate::websocket::image_msg::ImageRequest>,
-    pub left_eye_stream_req: Sender<crate::websocket::image_msg::ImageRequest>,
-    pub right_eye_stream_req: Sender<crate::websocket::image_msg::ImageRequest>,
+    pub face_frame_hub: FrameHub,
+    pub left_eye_frame_hub: FrameHub,
+    pub right_eye_frame_hub: FrameHub,
+    pub stereo_frame_hub: FrameHub<Arc<StereoFrame>>,
+    pub stereo_stats: Arc<Mutex<StereoStats>>,
+    // 左右眼配对容差，单位微秒
+    pub stereo_tolerance: Arc<AtomicU64>,
+    pub face_wifi_connected: Arc<AtomicBool>,
+    pub left_eye_wifi_connected: Arc<AtomicBool>,
+    pub right_eye_wifi_connected: Arc<AtomicBool>,
+    pub face_status: Arc<StatusCell>,
+    pub left_eye_status: Arc<StatusCell>,
+    pub right_eye_status: Arc<StatusCell>,
+    pub face_stats: Arc<Mutex<ConnectionStats>>,
+    pub left_eye_stats: Arc<Mutex<ConnectionStats>>,
+    pub right_eye_stats: Arc<Mutex<ConnectionStats>>,
+    pub face_latency: Arc<Mutex<LatencyStats>>,
+    pub left_eye_latency: Arc<Mutex<LatencyStats>>,
+    pub right_eye_latency: Arc<Mutex<LatencyStats>>,
     pub face_setting_req : Sender<crate::websocket::image_msg::StreamSettingRequest>,
     pub left_eye_setting_req : Sender<crate::websocket::image_msg::StreamSettingRequest>,
     pub right_eye_setting_req : Sender<crate::websocket::image_msg::StreamSettingRequest>,
@@ -20,11 +36,22 @@ pub struct ImageStreamState {
     pub right_eye_setting_resp: Arc::<Mutex<BusReader<crate::websocket::image_msg::StreamSettingResponse>>>,
 }
 
+// 追踪结果的分发
+pub struct TrackingState {
+    pub face_blendshape_hub: FrameHub<Arc<FaceBlendshapes>>,
+    pub left_eye_state_hub: FrameHub<Arc<EyeState>>,
+    pub right_eye_state_hub: FrameHub<Arc<EyeState>>,
+    pub left_eye_calibration: Arc<Mutex<Option<EyeCalibrationSession>>>,
+    pub right_eye_calibration: Arc<Mutex<Option<EyeCalibrationSession>>>,
+}
+
 pub struct SerialState {
     pub global_req_tx: Sender<crate::serial::serial_msg::SerialRequest>,
     pub global_write_tx: Sender<crate::serial::serial_msg::SerialSendPacket>,
     pub global_resp_rx: Arc<Mutex<BusReader<crate::serial::serial_msg::SerialResponse>>>,
     pub global_msg_rx: Arc<Mutex<BusReader<crate::serial::serial_msg::SerialMessage>>>,
+    // 串口上设备的类型，未连接为 DEVICE_TYPE_UNKNOWN
+    pub device_type: Arc<AtomicI32>,
 }
 
 pub fn init_device<R: Runtime>(app: &AppHandle<R>) {
@@ -40,65 +67,164 @@ pub fn init_device<R: Runtime>(app: &AppHandle<R>) {
         global_write_tx,
         global_resp_rx: Arc::new(Mutex::new(global_resp_rx)),
         global_msg_rx: Arc::new(Mutex::new(global_msg_rx)),
+        device_type: serial.get_device_type(),
     };
 
     // init face image stream
     let face_image_msg_rx = serial.get_message_rx();
+    let face_ip = FACE_CONFIG.read().unwrap().functional.wifi_ip.clone();
+    let face_source = create_frame_source(&FACE_CONFIG.read().unwrap().functional.source, DEVICE_TYPE_FACE, face_ip.clone());
     let mut face_image_stream = crate::websocket::image_stream::ImageStream::new(
         face_image_msg_rx, 
-        FACE_CONFIG.functional.wifi_ip.clone(), 
+        serial_state.device_type.clone(),
+        face_source,
+        face_ip,
         DEVICE_TYPE_FACE,
         app.clone());
-    let face_image_stream_request_tx = face_image_stream.get_request_tx();
-    let face_image_stream_response_rx = face_image_stream.get_response_rx();
+    let face_frame_hub = face_image_stream.get_frame_hub();
+    let face_wifi_connected = face_image_stream.get_connected_flag();
+    let face_status = face_image_stream.get_status_cell();
+    let face_stats = face_image_stream.get_stats();
+    let face_latency = face_image_stream.get_latency();
     let face_setting_request_tx = face_image_stream.get_settings_tx();
     let face_setting_response_rx = face_image_stream.get_setting_response_rx();
+    let _ = face_setting_request_tx.send(StreamSettingRequest::SetPreprocess(
+        FACE_CONFIG.read().unwrap().functional.preprocess.clone(),
+    ));
     std::thread::spawn(move || {
         face_image_stream.start();
     });
 
     // init left eye image stream
     let left_eye_image_msg_rx = serial.get_message_rx();
+    let left_eye_ip = EYE_CONFIG.read().unwrap().functional.left_ip.clone();
+    let left_eye_source = create_frame_source(&EYE_CONFIG.read().unwrap().functional.left_source, DEVICE_TYPE_LEFT_EYE, left_eye_ip.clone());
     let mut left_eye_image_stream = crate::websocket::image_stream::ImageStream::new(
         left_eye_image_msg_rx, 
-        EYE_CONFIG.functional.left_ip.clone(),
+        serial_state.device_type.clone(),
+        left_eye_source,
+        left_eye_ip,
         DEVICE_TYPE_LEFT_EYE,
         app.clone());
-    let left_eye_image_stream_request_tx = left_eye_image_stream.get_request_tx();
-    let left_eye_image_stream_response_rx = left_eye_image_stream.get_response_rx();
+    let left_eye_frame_hub = left_eye_image_stream.get_frame_hub();
+    let left_eye_wifi_connected = left_eye_image_stream.get_connected_flag();
+    let left_eye_status = left_eye_image_stream.get_status_cell();
+    let left_eye_stats = left_eye_image_stream.get_stats();
+    let left_eye_latency = left_eye_image_stream.get_latency();
     let left_eye_setting_request_tx = left_eye_image_stream.get_settings_tx();
     let left_eye_setting_response_rx = left_eye_image_stream.get_setting_response_rx();
+    let _ = left_eye_setting_request_tx.send(StreamSettingRequest::SetFlip(
+        EYE_CONFIG.read().unwrap().functional.left_flip_x,
+        EYE_CONFIG.read().unwrap().functional.flip_y,
+    ));
+    let _ = left_eye_setting_request_tx.send(StreamSettingRequest::SetPreprocess(
+        EYE_CONFIG.read().unwrap().functional.left_preprocess.clone(),
+    ));
     std::thread::spawn(move || {
         left_eye_image_stream.start();
     });
 
     // init right eye image stream
     let right_eye_image_msg_rx = serial.get_message_rx();
+    let right_eye_ip = EYE_CONFIG.read().unwrap().functional.right_ip.clone();
+    let right_eye_source = create_frame_source(&EYE_CONFIG.read().unwrap().functional.right_source, DEVICE_TYPE_RIGHT_EYE, right_eye_ip.clone());
     let mut right_eye_image_stream = crate::websocket::image_stream::ImageStream::new(
         right_eye_image_msg_rx, 
-        EYE_CONFIG.functional.right_ip.clone(),
+        serial_state.device_type.clone(),
+        right_eye_source,
+        right_eye_ip,
         DEVICE_TYPE_RIGHT_EYE,
         app.clone());
-    let right_eye_image_stream_request_tx = right_eye_image_stream.get_request_tx();
-    let right_eye_image_stream_response_rx = right_eye_image_stream.get_response_rx();
+    let right_eye_frame_hub = right_eye_image_stream.get_frame_hub();
+    let right_eye_wifi_connected = right_eye_image_stream.get_connected_flag();
+    let right_eye_status = right_eye_image_stream.get_status_cell();
+    let right_eye_stats = right_eye_image_stream.get_stats();
+    let right_eye_latency = right_eye_image_stream.get_latency();
     let right_eye_setting_request_tx = right_eye_image_stream.get_settings_tx();
     let right_eye_setting_response_rx = right_eye_image_stream.get_setting_response_rx();
+    let _ = right_eye_setting_request_tx.send(StreamSettingRequest::SetFlip(
+        EYE_CONFIG.read().unwrap().functional.right_flip_x,
+        EYE_CONFIG.read().unwrap().functional.flip_y,
+    ));
+    let _ = right_eye_setting_request_tx.send(StreamSettingRequest::SetPreprocess(
+        EYE_CONFIG.read().unwrap().functional.right_preprocess.clone(),
+    ));
     std::thread::spawn(move || {
         right_eye_image_stream.start();
     });
 
+    // init stereo synchronizer
+    let mut stereo_synchronizer = StereoSynchronizer::new(
+        left_eye_frame_hub.clone(),
+        right_eye_frame_hub.clone(),
+        EYE_CONFIG.read().unwrap().functional.stereo_tolerance_ms,
+    );
+    let stereo_frame_hub = stereo_synchronizer.get_stereo_hub();
+    let stereo_stats = stereo_synchronizer.get_stats();
+    let stereo_tolerance = stereo_synchronizer.get_tolerance();
+    std::thread::spawn(move || {
+        stereo_synchronizer.start();
+    });
+
+    // init face tracker
+    let mut face_tracker = FaceTracker::new(face_frame_hub.clone(), face_latency.clone(), app.clone());
+    let face_blendshape_hub = face_tracker.get_result_hub();
+    std::thread::spawn(move || {
+        face_tracker.start();
+    });
+
+    // init eye trackers
+    let left_eye_input = EyeFrameInput::new(
+        DEVICE_TYPE_LEFT_EYE,
+        left_eye_frame_hub.clone(),
+        stereo_frame_hub.clone(),
+        left_eye_wifi_connected.clone(),
+        right_eye_wifi_connected.clone(),
+    );
+    let mut left_eye_tracker = EyeTracker::new(left_eye_input, left_eye_latency.clone(), app.clone());
+    let left_eye_state_hub = left_eye_tracker.get_result_hub();
+    let left_eye_calibration = left_eye_tracker.get_calibration_session();
+    std::thread::spawn(move || {
+        left_eye_tracker.start();
+    });
+    let right_eye_input = EyeFrameInput::new(
+        DEVICE_TYPE_RIGHT_EYE,
+        right_eye_frame_hub.clone(),
+        stereo_frame_hub.clone(),
+        left_eye_wifi_connected.clone(),
+        right_eye_wifi_connected.clone(),
+    );
+    let mut right_eye_tracker = EyeTracker::new(right_eye_input, right_eye_latency.clone(), app.clone());
+    let right_eye_state_hub = right_eye_tracker.get_result_hub();
+    let right_eye_calibration = right_eye_tracker.get_calibration_session();
+    std::thread::spawn(move || {
+        right_eye_tracker.start();
+    });
+
     // start serial
     std::thread::spawn(move || {
         serial.start();
     });
 
     let image_stream_state = ImageStreamState {
-        face_stream_resp: Arc::new(Mutex::new(face_image_stream_response_rx)),
-        left_eye_stream_resp: Arc::new(Mutex::new(left_eye_image_stream_response_rx)),
-        right_eye_stream_resp: Arc::new(Mutex::new(right_eye_image_stream_response_rx)),
-        face_stream_req: face_image_stream_request_tx,
-        left_eye_stream_req: left_eye_image_stream_request_tx,
-        right_eye_stream_req: right_eye_image_stream_request_tx,
+        face_frame_hub,
+        left_eye_frame_hub,
+        right_eye_frame_hub,
+        stereo_frame_hub,
+        stereo_stats,
+        stereo_tolerance,
+        face_wifi_connected,
+        left_eye_wifi_connected,
+        right_eye_wifi_connected,
+        face_status,
+        left_eye_status,
+        right_eye_status,
+        face_stats,
+        left_eye_stats,
+        right_eye_stats,
+        face_latency,
+        left_eye_latency,
+        right_eye_latency,
         face_setting_req: face_setting_request_tx,
         left_eye_setting_req: left_eye_setting_request_tx,
         right_eye_setting_req: right_eye_setting_request_tx,
@@ -108,5 +234,13 @@ pub fn init_device<R: Runtime>(app: &AppHandle<R>) {
     };
 
     app.manage(image_stream_state);
+    app.manage(TrackingState {
+        face_blendshape_hub,
+        left_eye_state_hub,
+        right_eye_state_hub,
+        left_eye_calibration,
+        right_eye_calibration,
+    });
+    app.manage(PreviewState::default());
     app.manage(serial_state);
 }
\ No newline at end of file
@@ -1,11 +1,17 @@
 use serde::Serialize;
 use tauri::{ipc::Channel, AppHandle, Emitter, Manager, Runtime};
 use crossbeam::channel::{Receiver, Sender};
-use std::sync::{mpsc::TryRecvError, Mutex};
-use crate::{serial::serial_msg::{self, FlashCommand, SerialRequest, SerialResponse, SerialSendPacket, WifiConfig}, websocket::image_msg::{ImageRequest, ImageResponse, StreamSettingRequest, StreamSettingResponse}};
+use std::{sync::{atomic::Ordering, mpsc::TryRecvError, Arc, Mutex}, time::{Duration, Instant}};
+use crossbeam::channel::RecvTimeoutError;
+use opencv::core::MatTraitConst;
+use crate::{paper_tracker_config::config::{write_eye_config, CalibrationField, ExpressionParams, write_face_config, EYE_CONFIG, FACE_CONFIG}, serial::serial_msg::{self, FlashCommand, SerialRequest, SerialResponse, SerialSendPacket, WifiConfig}, websocket::{frame_hub::{FrameHub, SubscriberPolicy}, image_msg::{PreviewSettings, StatusCell, StreamSettingRequest, StreamSettingResponse, WifiControlMessage, PREVIEW_FPS}, preprocess::PreprocessStage}};
+use crate::utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE};
+use crate::websocket::{latency::LatencyStats, reconnect::ConnectionStats, stereo_sync::StereoStats, ws_source::WebSocketSource};
+use crate::algorithm::{eye_calibration::{EyeCalibration, EyeCalibrationSession, EyeCalibrationStatus}, expression::Expression, eye_tracker::EyeDetectorKind, filter::FilterKind, one_euro_filter::OneEuroParams, pupil_detector::PupilDetectorConfig, response_curve::{sample_unit, ResponseCurve}};
+use url::Url;
 use ftlog::*;
 
-use super::init::{ImageStreamState, SerialState};
+use super::{init::{ImageStreamState, SerialState, TrackingState}, preview::PreviewState, transport::{get_setting_req, select_transport, ControlTransport}};
 
 
 
@@ -173,12 +179,17 @@ pub async fn write_wifi_info<R: Runtime>(app: tauri::AppHandle<R>, ssid: String,
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "data")]
 pub enum StreamEvent {
-    Image {
-        base64: String,
+    // 图像数据通过 papertracker://localhost/<handle> 获取，这里只发送元数据
+    Frame {
         device: String,
+        handle: u64,
+        seq: u64,
+        width: i32,
+        height: i32,
     },
     Status {
         serial: bool,
+        wifi: bool,
         ip: String,
         battery: f32,
         brightness: i32,
@@ -189,168 +200,234 @@ pub enum StreamEvent {
     },
 }
 
-#[tauri::command]
-pub fn start_face_image_stream<R: Runtime>(
-    app: tauri::AppHandle<R>, 
-    on_event: Channel<StreamEvent>
-) {
-    info!("Starting Face Image Stream");
-    
-    // 发送初始连接消息
-    on_event.send(StreamEvent::Log {
-        message: "Face image stream connected".to_string()
-    }).unwrap();
-    
-    let state = app.state::<ImageStreamState>();
-    let face_stream_req = state.face_stream_req.clone();
-    let face_stream_resp = state.face_stream_resp.clone();
-    
+// 预览线程：新帧到达即推送，超过目标帧率的帧直接跳过
+// 调用 stop、窗口关闭或 Channel 失效时线程退出并退订
+fn spawn_preview_stream<R: Runtime>(
+    app: AppHandle<R>,
+    window: &str,
+    device: &'static str,
+    frame_hub: FrameHub,
+    status: Arc<StatusCell>,
+    on_event: Channel<StreamEvent>,
+    settings: Option<PreviewSettings>,
+) -> u64 {
+    let (handle, stop, settings) = app.state::<PreviewState>().register(device, window, settings.unwrap_or_default());
+    // 预览只需要最新一帧，不影响其他订阅者
+    let subscription = frame_hub.subscribe(&format!("preview:{}:{}", device, handle), SubscriberPolicy::LatestOnly);
     std::thread::spawn(move || {
-        let mut face_stream_resp = face_stream_resp.lock().unwrap();
-        
-        loop {
-            if let Err(e) = face_stream_req.send(ImageRequest::GetImageBase64) {
-                error!("Failed to send face image request: {}", e);
-                on_event.send(StreamEvent::Log {
-                    message: format!("Failed to send request: {}", e)
-                }).ok();
-                continue;
+        let mut last_sent: Option<Instant> = None;
+        let mut status_version: Option<u64> = None;
+        while !stop.load(Ordering::Relaxed) {
+            // 状态变化时推送，新订阅的窗口会立即收到一次当前状态
+            let (version, current) = status.get();
+            if status_version != Some(version) {
+                let sent = on_event.send(StreamEvent::Status {
+                    serial: current.serial,
+                    wifi: current.wifi,
+                    ip: current.ip,
+                    battery: current.battery,
+                    brightness: current.brightness,
+                    device_type: current.device_type,
+                });
+                if sent.is_err() {
+                    info!("Preview {} channel closed", handle);
+                    break;
+                }
+                status_version = Some(version);
             }
-            
-            match face_stream_resp.try_recv() {
-                Ok(ImageResponse::Base64ImageData(data)) => {
-                    // 将 Vec<u8> 转换回 base64 字符串
-                    let base64_string = String::from_utf8(data).unwrap_or_else(|_| {
-                        error!("Invalid UTF-8 in base64 data");
-                        String::new()
-                    });
-                    
-                    // 发送图像事件
-                    on_event.send(StreamEvent::Image {
-                        base64: base64_string,
-                        device: "face".to_string(),
-                    }).unwrap();
+            match subscription.recv_timeout(Duration::from_millis(200)) {
+                Ok(frame) => {
+                    let settings = settings.read().unwrap().clone();
+                    let fps = if settings.fps > 0.0 { settings.fps } else { PREVIEW_FPS };
+                    let min_interval = Duration::from_secs_f64(1.0 / fps);
+                    if last_sent.map(|last_sent| last_sent.elapsed() < min_interval).unwrap_or(false) {
+                        continue;
+                    }
+                    // 默认设置下同一帧只编码一次，无变换时直接使用设备原始 JPEG
+                    match frame.encode_preview(&settings) {
+                        Ok(jpeg) => {
+                            let seq = app.state::<PreviewState>().publish(handle, device, jpeg);
+                            // 发送帧元数据事件，尺寸为实际发送的 JPEG 尺寸
+                            let (width, height) = frame.preview_size(&settings);
+                            let sent = on_event.send(StreamEvent::Frame {
+                                device: device.to_string(),
+                                handle,
+                                seq,
+                                width,
+                                height,
+                            });
+                            if sent.is_err() {
+                                info!("Preview {} channel closed", handle);
+                                break;
+                            }
+                            last_sent = Some(Instant::now());
+                        }
+                        Err(e) => {
+                            error!("Failed to encode {} preview: {}", device, e);
+                        }
+                    }
                 }
-                Err(TryRecvError::Disconnected) => {
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
                     error!("Failed to receive data from disconnected channel");
                     break;
                 }
-                _ => ()
             }
-            
-            std::thread::sleep(std::time::Duration::from_millis(1));
         }
+        drop(subscription);
+        app.state::<PreviewState>().finish(handle);
+        info!("Preview {} of {} stopped", handle, device);
     });
+    handle
+}
+
+#[tauri::command]
+pub fn start_face_image_stream<R: Runtime>(
+    app: tauri::AppHandle<R>, 
+    window: tauri::Window<R>,
+    on_event: Channel<StreamEvent>,
+    settings: Option<PreviewSettings>
+) -> u64 {
+    info!("Starting Face Image Stream");
+    
+    // 发送初始连接消息
+    on_event.send(StreamEvent::Log {
+        message: "Face image stream connected".to_string()
+    }).ok();
+    
+    let frame_hub = app.state::<ImageStreamState>().face_frame_hub.clone();
+    let status = app.state::<ImageStreamState>().face_status.clone();
+    spawn_preview_stream(app, window.label(), "face", frame_hub, status, on_event, settings)
 }
 
 
 #[tauri::command]
 pub fn start_left_eye_image_stream<R: Runtime>(
     app: tauri::AppHandle<R>, 
-    on_event: Channel<StreamEvent>
-) {
+    window: tauri::Window<R>,
+    on_event: Channel<StreamEvent>,
+    settings: Option<PreviewSettings>
+) -> u64 {
     info!("Starting Left Eye Image Stream");
     
     // 发送初始连接消息
     on_event.send(StreamEvent::Log {
         message: "Left Eye image stream connected".to_string()
-    }).unwrap();
+    }).ok();
 
-    let state = app.state::<ImageStreamState>();
-    let left_eye_stream_req = state.left_eye_stream_req.clone();
-    let left_eye_stream_resp = state.left_eye_stream_resp.clone();
-    std::thread::spawn(move || {
-        let mut left_eye_stream_resp = left_eye_stream_resp.lock().unwrap();
-        loop {
-            if let Err(e) = left_eye_stream_req.send(ImageRequest::GetImageBase64) {
-                error!("Failed to send left eye image request: {}", e);
-                on_event.send(StreamEvent::Log {
-                    message: format!("Failed to send request: {}", e)
-                }).ok();
-                continue;
-            }
-            match left_eye_stream_resp.try_recv() {
-                Ok(ImageResponse::Base64ImageData(data)) => {
-                    // 将 Vec<u8> 转换回 base64 字符串
-                    let base64_string = String::from_utf8(data).unwrap_or_else(|_| {
-                        error!("Invalid UTF-8 in base64 data");
-                        String::new()
-                    });
-                    
-                    // 发送图像事件
-                    on_event.send(StreamEvent::Image {
-                        base64: base64_string,
-                        device: "left_eye".to_string(),
-                    }).unwrap();
-                }
-                Err(TryRecvError::Disconnected) => {
-                    error!("Failed to receive data from disconnected channel");
-                    break;
-                }
-                _ => ()
-            }
-            std::thread::sleep(std::time::Duration::from_millis(1));
-        }
-    });
+    let frame_hub = app.state::<ImageStreamState>().left_eye_frame_hub.clone();
+    let status = app.state::<ImageStreamState>().left_eye_status.clone();
+    spawn_preview_stream(app, window.label(), "left_eye", frame_hub, status, on_event, settings)
 }
 
 #[tauri::command]
 pub fn start_right_eye_image_stream<R: Runtime>(
     app: tauri::AppHandle<R>, 
-    on_event: Channel<StreamEvent>
-) {
-    let state = app.state::<ImageStreamState>();
-    let right_eye_stream_req = state.right_eye_stream_req.clone();
-    let right_eye_stream_resp = state.right_eye_stream_resp.clone();
-    std::thread::spawn(move || {
-        let mut right_eye_stream_resp = right_eye_stream_resp.lock().unwrap();
-        loop {
-            if let Err(e) = right_eye_stream_req.send(ImageRequest::GetImageBase64) {
-                error!("Failed to send right eye image request: {}", e);
-                on_event.send(StreamEvent::Log {
-                    message: format!("Failed to send request: {}", e)
-                }).ok();
-                continue;
-            }
-            match right_eye_stream_resp.try_recv() {
-                Ok(ImageResponse::Base64ImageData(data)) => {
-                    // 将 Vec<u8> 转换回 base64 字符串
-                    let base64_string = String::from_utf8(data).unwrap_or_else(|_| {
-                        error!("Invalid UTF-8 in base64 data");
-                        String::new()
-                    });
-                    
-                    // 发送图像事件
-                    on_event.send(StreamEvent::Image {
-                        base64: base64_string,
-                        device: "right_eye".to_string(),
-                    }).unwrap();
-                }
-                Err(TryRecvError::Disconnected) => {
-                    error!("Failed to receive data from disconnected channel");
-                    break;
-                }
-                _ => ()
-            }
-            std::thread::sleep(std::time::Duration::from_millis(1));
-        }
-    });
+    window: tauri::Window<R>,
+    on_event: Channel<StreamEvent>,
+    settings: Option<PreviewSettings>
+) -> u64 {
+    let frame_hub = app.state::<ImageStreamState>().right_eye_frame_hub.clone();
+    let status = app.state::<ImageStreamState>().right_eye_status.clone();
+    spawn_preview_stream(app, window.label(), "right_eye", frame_hub, status, on_event, settings)
+}
+
+#[tauri::command]
+pub fn stop_image_stream<R: Runtime>(app: tauri::AppHandle<R>, handle: u64) -> Result<(), String> {
+    if app.state::<PreviewState>().stop(handle) {
+        Ok(())
+    } else {
+        Err(format!("Image stream {} does not exist", handle))
+    }
+}
+
+#[tauri::command]
+pub fn set_preview_settings<R: Runtime>(app: tauri::AppHandle<R>, handle: u64, settings: PreviewSettings) -> Result<(), String> {
+    if app.state::<PreviewState>().update_settings(handle, settings) {
+        Ok(())
+    } else {
+        Err(format!("Image stream {} does not exist", handle))
+    }
+}
+
+#[tauri::command]
+pub fn stop_window_image_streams<R: Runtime>(app: tauri::AppHandle<R>, window: tauri::Window<R>) -> usize {
+    app.state::<PreviewState>().stop_window(window.label())
+}
+
+fn send_wifi_control<R: Runtime>(app: &AppHandle<R>, device_type: i32, message: WifiControlMessage) -> Result<(), String> {
+    let send_tx = get_setting_req(&app.state::<ImageStreamState>(), device_type)?;
+    if let Err(e) = send_tx.send(StreamSettingRequest::SendControl(message)) {
+        return Err(format!("Failed to send control request: {}", e));
+    }
+    Ok(())
 }
 
 #[tauri::command]
 pub fn set_brightness(
     app: tauri::AppHandle<impl Runtime>, 
-    brightness: u8
+    brightness: u8,
+    device_type: i32
 ) -> Result<(), String> {
-    let state = app.state::<SerialState>();
-    let write_tx = state.global_write_tx.clone();
-    if let Err(e) = write_tx.send(SerialSendPacket::Brightness(brightness as i32)) {
-        return Err(format!("Failed to send brightness request to ESP32: {}", e));
+    match select_transport(&app, device_type)? {
+        ControlTransport::Serial => {
+            let state = app.state::<SerialState>();
+            let write_tx = state.global_write_tx.clone();
+            if let Err(e) = write_tx.send(SerialSendPacket::Brightness(brightness as i32)) {
+                return Err(format!("Failed to send brightness request to ESP32: {}", e));
+            }
+        }
+        ControlTransport::Wifi => {
+            send_wifi_control(&app, device_type, WifiControlMessage::SetBrightness { value: brightness as i32 })?;
+        }
     }
     info!("Brightness set to {}", brightness);
     Ok(())
 }
 
+#[tauri::command]
+pub fn set_energy_mode(
+    app: tauri::AppHandle<impl Runtime>,
+    mode: i32,
+    device_type: i32
+) -> Result<(), String> {
+    match select_transport(&app, device_type)? {
+        // 串口固件协议只有 WIFI 配置和补光两种写入包，没有功耗模式
+        ControlTransport::Serial => Err("串口协议不支持设置功耗模式，请拔掉数据线后通过WIFI设置".to_string()),
+        ControlTransport::Wifi => {
+            send_wifi_control(&app, device_type, WifiControlMessage::SetEnergyMode { mode })?;
+            info!("Energy mode set to {}", mode);
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn restart_device<R: Runtime>(app: tauri::AppHandle<R>, device_type: i32) -> Result<(), String> {
+    match select_transport(&app, device_type)? {
+        ControlTransport::Serial => restart_esp32(app).await,
+        ControlTransport::Wifi => {
+            send_wifi_control(&app, device_type, WifiControlMessage::Restart)?;
+            info!("Restart request sent to device {} over WiFi", device_type);
+            Ok(())
+        }
+    }
+}
+
+// 状态由图像流更新后推送；WIFI 下通过 websocket 文本帧返回，串口下重新发布最近的状态包
+#[tauri::command]
+pub fn query_device_status(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<(), String> {
+    match select_transport(&app, device_type)? {
+        ControlTransport::Serial => app.state::<SerialState>().global_req_tx
+            .send(SerialRequest::ResendDeviceStatus)
+            .map_err(|e| format!("Failed to send status request: {}", e)),
+        ControlTransport::Wifi => send_wifi_control(&app, device_type, WifiControlMessage::GetStatus),
+    }
+}
+
 
 #[tauri::command]
 pub fn set_rotation(
@@ -369,4 +446,515 @@ pub fn set_rotation(
         return Err(format!("Failed to send rotation request: {}", e));
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub fn set_flip(
+    app: tauri::AppHandle<impl Runtime>,
+    left_flip_x: bool,
+    right_flip_x: bool,
+    flip_y: bool
+) -> Result<(), String> {
+    let state = app.state::<ImageStreamState>();
+    if let Err(e) = state.left_eye_setting_req.send(StreamSettingRequest::SetFlip(left_flip_x, flip_y)) {
+        return Err(format!("Failed to send left eye flip request: {}", e));
+    }
+    if let Err(e) = state.right_eye_setting_req.send(StreamSettingRequest::SetFlip(right_flip_x, flip_y)) {
+        return Err(format!("Failed to send right eye flip request: {}", e));
+    }
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        eye_config.functional.left_flip_x = left_flip_x;
+        eye_config.functional.right_flip_x = right_flip_x;
+        eye_config.functional.flip_y = flip_y;
+        eye_config.modified = true;
+    }
+    if let Err(e) = write_eye_config() {
+        return Err(format!("保存眼追配置失败: {}", e));
+    }
+    info!("Flip set to: left x = {}, right x = {}, y = {}", left_flip_x, right_flip_x, flip_y);
+    Ok(())
+}
+
+// 更新并保存某个设备的预处理链配置
+fn save_preprocess_config(device_type: i32, update: impl FnOnce(&mut Vec<PreprocessStage>)) -> Result<(), String> {
+    match device_type {
+        DEVICE_TYPE_FACE => {
+            {
+                let mut face_config = FACE_CONFIG.write().unwrap();
+                update(&mut face_config.functional.preprocess);
+                face_config.modified = true;
+            }
+            write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+        }
+        DEVICE_TYPE_LEFT_EYE | DEVICE_TYPE_RIGHT_EYE => {
+            {
+                let mut eye_config = EYE_CONFIG.write().unwrap();
+                if device_type == DEVICE_TYPE_LEFT_EYE {
+                    update(&mut eye_config.functional.left_preprocess);
+                } else {
+                    update(&mut eye_config.functional.right_preprocess);
+                }
+                eye_config.modified = true;
+            }
+            write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+        }
+        _ => Err("Invalid device type".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn set_preprocess_pipeline(
+    app: tauri::AppHandle<impl Runtime>,
+    stages: Vec<PreprocessStage>,
+    device_type: i32
+) -> Result<(), String> {
+    let state = app.state::<ImageStreamState>();
+    let send_tx = match device_type {
+        1 => state.face_setting_req.clone(),
+        2 => state.left_eye_setting_req.clone(),
+        3 => state.right_eye_setting_req.clone(),
+        _ => return Err("Invalid device type".to_string()),
+    };
+    if let Err(e) = send_tx.send(StreamSettingRequest::SetPreprocess(stages.clone())) {
+        return Err(format!("Failed to send preprocess request: {}", e));
+    }
+    save_preprocess_config(device_type, |config| *config = stages)
+}
+
+#[tauri::command]
+pub fn set_preprocess_stage_enabled(
+    app: tauri::AppHandle<impl Runtime>,
+    index: usize,
+    enabled: bool,
+    device_type: i32
+) -> Result<(), String> {
+    let state = app.state::<ImageStreamState>();
+    let send_tx = match device_type {
+        1 => state.face_setting_req.clone(),
+        2 => state.left_eye_setting_req.clone(),
+        3 => state.right_eye_setting_req.clone(),
+        _ => return Err("Invalid device type".to_string()),
+    };
+    if let Err(e) = send_tx.send(StreamSettingRequest::SetPreprocessStageEnabled(index, enabled)) {
+        return Err(format!("Failed to send preprocess request: {}", e));
+    }
+    save_preprocess_config(device_type, |config| {
+        if let Some(stage) = config.get_mut(index) {
+            stage.enabled = enabled;
+        }
+    })
+}
+
+#[tauri::command]
+pub fn set_device_ip(
+    app: tauri::AppHandle<impl Runtime>,
+    ip: String,
+    device_type: i32
+) -> Result<(), String> {
+    let ip = ip.trim().to_string();
+    // 与重连时使用同一套地址转换规则，确保保存的地址可以连接
+    let url = WebSocketSource::new(device_type, ip.clone()).get_connect_url_from_self_ip();
+    match Url::parse(&url) {
+        Ok(url) if (url.scheme() == "ws" || url.scheme() == "wss") && url.host_str().map(|host| !host.is_empty()).unwrap_or(false) => (),
+        _ => return Err(format!("无效的设备地址: {}", ip)),
+    }
+    let send_tx = get_setting_req(&app.state::<ImageStreamState>(), device_type)?;
+    if let Err(e) = send_tx.send(StreamSettingRequest::SetAddress(ip.clone())) {
+        return Err(format!("Failed to send address request: {}", e));
+    }
+    match device_type {
+        DEVICE_TYPE_FACE => {
+            {
+                let mut face_config = FACE_CONFIG.write().unwrap();
+                face_config.functional.wifi_ip = ip.clone();
+                face_config.modified = true;
+            }
+            write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))?;
+        }
+        _ => {
+            {
+                let mut eye_config = EYE_CONFIG.write().unwrap();
+                if device_type == DEVICE_TYPE_LEFT_EYE {
+                    eye_config.functional.left_ip = ip.clone();
+                } else {
+                    eye_config.functional.right_ip = ip.clone();
+                }
+                eye_config.modified = true;
+            }
+            write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))?;
+        }
+    }
+    info!("Device {} address set to {}", device_type, ip);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_stream_stats(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<ConnectionStats, String> {
+    let state = app.state::<ImageStreamState>();
+    let stats = match device_type {
+        DEVICE_TYPE_FACE => state.face_stats.clone(),
+        DEVICE_TYPE_LEFT_EYE => state.left_eye_stats.clone(),
+        DEVICE_TYPE_RIGHT_EYE => state.right_eye_stats.clone(),
+        _ => return Err("Invalid device type".to_string()),
+    };
+    let stats = stats.lock().unwrap().clone();
+    Ok(stats)
+}
+
+#[tauri::command]
+pub fn set_stereo_tolerance(
+    app: tauri::AppHandle<impl Runtime>,
+    tolerance_ms: f64
+) -> Result<(), String> {
+    if !tolerance_ms.is_finite() || tolerance_ms < 0.0 {
+        return Err(format!("无效的配对容差: {}", tolerance_ms));
+    }
+    let state = app.state::<ImageStreamState>();
+    state.stereo_tolerance.store((tolerance_ms * 1000.0) as u64, Ordering::Relaxed);
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        eye_config.functional.stereo_tolerance_ms = tolerance_ms;
+        eye_config.modified = true;
+    }
+    write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+}
+
+#[tauri::command]
+pub fn get_stereo_stats(app: tauri::AppHandle<impl Runtime>) -> StereoStats {
+    let state = app.state::<ImageStreamState>();
+    let stats = state.stereo_stats.lock().unwrap().clone();
+    stats
+}
+
+#[tauri::command]
+pub fn get_latency_stats(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<LatencyStats, String> {
+    let state = app.state::<ImageStreamState>();
+    let latency = match device_type {
+        DEVICE_TYPE_FACE => state.face_latency.clone(),
+        DEVICE_TYPE_LEFT_EYE => state.left_eye_latency.clone(),
+        DEVICE_TYPE_RIGHT_EYE => state.right_eye_latency.clone(),
+        _ => return Err("Invalid device type".to_string()),
+    };
+    let latency = latency.lock().unwrap().clone();
+    Ok(latency)
+}
+
+// 表情识别开关，关闭后面捕追踪线程退订图像
+#[tauri::command]
+pub fn set_face_tracking(enabled: bool) -> Result<(), String> {
+    {
+        let mut face_config = FACE_CONFIG.write().unwrap();
+        face_config.functional.tracking = enabled;
+        face_config.modified = true;
+    }
+    info!("Face tracking enabled: {}", enabled);
+    write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+}
+
+// 眼追开关，关闭后左右眼追踪线程退订图像
+#[tauri::command]
+pub fn set_eye_tracking(enabled: bool) -> Result<(), String> {
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        eye_config.functional.tracking = enabled;
+        eye_config.modified = true;
+    }
+    info!("Eye tracking enabled: {}", enabled);
+    write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+}
+
+// 眼追线程每帧读取配置，修改后下一帧即生效
+#[tauri::command]
+pub fn set_eye_detector(
+    detector: EyeDetectorKind,
+    device_type: i32
+) -> Result<(), String> {
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        match device_type {
+            DEVICE_TYPE_LEFT_EYE => eye_config.functional.left_detector = detector,
+            DEVICE_TYPE_RIGHT_EYE => eye_config.functional.right_detector = detector,
+            _ => return Err("Invalid device type".to_string()),
+        }
+        eye_config.modified = true;
+    }
+    info!("Eye {} detector set to {:?}", device_type, detector);
+    write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+}
+
+#[tauri::command]
+pub fn set_pupil_detector_config(config: PupilDetectorConfig) -> Result<(), String> {
+    if config.min_area_ratio < 0.0 || config.max_area_ratio <= config.min_area_ratio {
+        return Err("无效的瞳孔面积范围".to_string());
+    }
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        eye_config.functional.pupil_detector = config;
+        eye_config.modified = true;
+    }
+    write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+}
+
+// 面捕和眼追各自的滤波开关，两只眼共用一个，追踪线程每帧读取
+#[tauri::command]
+pub fn set_use_filter(enabled: bool, device_type: i32) -> Result<(), String> {
+    match device_type {
+        DEVICE_TYPE_FACE => {
+            {
+                let mut face_config = FACE_CONFIG.write().unwrap();
+                face_config.functional.use_filter = enabled;
+                face_config.modified = true;
+            }
+            info!("Face filter enabled: {}", enabled);
+            write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+        }
+        DEVICE_TYPE_LEFT_EYE | DEVICE_TYPE_RIGHT_EYE => {
+            {
+                let mut eye_config = EYE_CONFIG.write().unwrap();
+                eye_config.functional.use_filter = enabled;
+                eye_config.modified = true;
+            }
+            info!("Eye filter enabled: {}", enabled);
+            write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+        }
+        _ => Err("Invalid device type".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_use_filter(device_type: i32) -> Result<bool, String> {
+    match device_type {
+        DEVICE_TYPE_FACE => Ok(FACE_CONFIG.read().unwrap().functional.use_filter),
+        DEVICE_TYPE_LEFT_EYE | DEVICE_TYPE_RIGHT_EYE => Ok(EYE_CONFIG.read().unwrap().functional.use_filter),
+        _ => Err("Invalid device type".to_string()),
+    }
+}
+
+// channel 为表情名、gaze、openness，或 default（未单独设置的表情）；gaze 和 openness 保存在眼追配置中
+#[tauri::command]
+pub fn set_filter_kind(channel: String, kind: FilterKind) -> Result<(), String> {
+    match channel.as_str() {
+        "gaze" | "openness" => {
+            {
+                let mut eye_config = EYE_CONFIG.write().unwrap();
+                if channel == "gaze" {
+                    eye_config.functional.gaze_filter = kind;
+                } else {
+                    eye_config.functional.openness_filter = kind;
+                }
+                eye_config.modified = true;
+            }
+            info!("Filter of {} set to {:?}", channel, kind);
+            write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))
+        }
+        _ => {
+            {
+                let mut face_config = FACE_CONFIG.write().unwrap();
+                let filter = &mut face_config.filter;
+                if channel == "default" {
+                    filter.default_filter = kind;
+                } else {
+                    match Expression::from_id(&channel) {
+                        Some(expression) => {
+                            filter.expressions.insert(expression, kind);
+                        }
+                        None => return Err(format!("未知的滤波通道: {}", channel)),
+                    }
+                }
+                face_config.modified = true;
+            }
+            info!("Filter of {} set to {:?}", channel, kind);
+            write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+        }
+    }
+}
+
+// NaN 和无穷大也视为无效，避免写入配置后滤波输出 NaN
+#[tauri::command]
+pub fn set_one_euro_params(params: OneEuroParams) -> Result<(), String> {
+    let OneEuroParams { min_cutoff, beta, d_cutoff } = params;
+    if !(min_cutoff.is_finite() && min_cutoff > 0.0)
+        || !(d_cutoff.is_finite() && d_cutoff > 0.0)
+        || !(beta.is_finite() && beta >= 0.0)
+    {
+        return Err("无效的 One Euro 参数".to_string());
+    }
+    {
+        let mut face_config = FACE_CONFIG.write().unwrap();
+        face_config.filter.one_euro = params;
+        face_config.modified = true;
+    }
+    write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+}
+
+// 标定页滑块调用，param 为表情名；拖动过程中 persist 为 false 只修改内存，松开时再写入配置文件
+#[tauri::command]
+pub fn update_calibration(
+    param: String,
+    value: f64,
+    field: Option<CalibrationField>,
+    persist: Option<bool>
+) -> Result<(), String> {
+    let expression = Expression::from_id(&param).ok_or_else(|| format!("未知的表情参数: {}", param))?;
+    if !value.is_finite() {
+        return Err(format!("无效的标定值: {}", value));
+    }
+    let field = field.unwrap_or_default();
+    {
+        let mut face_config = FACE_CONFIG.write().unwrap();
+        let params = face_config.params.expressions.entry(expression).or_default();
+        match field {
+            CalibrationField::Gain => params.gain = value.max(0.0),
+            CalibrationField::Offset => params.offset = value,
+        }
+        face_config.modified = true;
+    }
+    if !persist.unwrap_or(true) {
+        return Ok(());
+    }
+    debug!("Calibration of {} {:?} set to {}", expression.id(), field, value);
+    write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+}
+
+// 标定页打开时读取当前的偏移、增益和响应曲线
+#[tauri::command]
+pub fn get_calibration(param: String) -> Result<ExpressionParams, String> {
+    let expression = Expression::from_id(&param).ok_or_else(|| format!("未知的表情参数: {}", param))?;
+    Ok(FACE_CONFIG.read().unwrap().params.get(expression))
+}
+
+#[tauri::command]
+pub fn set_response_curve(param: String, curve: ResponseCurve) -> Result<(), String> {
+    let expression = Expression::from_id(&param).ok_or_else(|| format!("未知的表情参数: {}", param))?;
+    curve.validate()?;
+    {
+        let mut face_config = FACE_CONFIG.write().unwrap();
+        face_config.params.expressions.entry(expression).or_default().curve = curve;
+        face_config.modified = true;
+    }
+    info!("Response curve of {} updated", expression.id());
+    write_face_config().map_err(|e| format!("保存面捕配置失败: {}", e))
+}
+
+#[derive(Clone, Serialize)]
+pub struct ResponseCurveSamples {
+    pub name: &'static str,
+    pub curve: ResponseCurve,
+    // 只有响应曲线
+    pub curve_points: Vec<[f64; 2]>,
+    // 偏移、增益和响应曲线合在一起的输入输出
+    pub response_points: Vec<[f64; 2]>,
+}
+
+// 界面绘图用，samples 为每条曲线的采样点数
+#[tauri::command]
+pub fn get_response_curves(samples: Option<usize>) -> Vec<ResponseCurveSamples> {
+    let samples = samples.unwrap_or(51).clamp(2, 1001);
+    let face_config = FACE_CONFIG.read().unwrap();
+    Expression::ALL.iter().map(|expression| {
+        let params = face_config.params.get(*expression);
+        ResponseCurveSamples {
+            name: expression.id(),
+            curve_points: params.curve.sample(samples),
+            response_points: sample_unit(samples, |x| params.apply(x)),
+            curve: params.curve,
+        }
+    }).collect()
+}
+
+fn eye_calibration_session(
+    app: &tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<Arc<Mutex<Option<EyeCalibrationSession>>>, String> {
+    let state = app.state::<TrackingState>();
+    match device_type {
+        DEVICE_TYPE_LEFT_EYE => Ok(state.left_eye_calibration.clone()),
+        DEVICE_TYPE_RIGHT_EYE => Ok(state.right_eye_calibration.clone()),
+        _ => Err("Invalid device type".to_string()),
+    }
+}
+
+// 开始眼部校准，center_only 为 true 时只重新设置中心，沿用已有的范围
+#[tauri::command]
+pub fn start_eye_calibration(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32,
+    center_only: Option<bool>
+) -> Result<EyeCalibrationStatus, String> {
+    let session = eye_calibration_session(&app, device_type)?;
+    let new_session = if center_only.unwrap_or(false) {
+        let base = EyeCalibration::from_params(&EYE_CONFIG.read().unwrap().params, device_type)
+            .ok_or_else(|| "请先完成完整校准".to_string())?;
+        EyeCalibrationSession::center_only(base)
+    } else {
+        EyeCalibrationSession::new()
+    };
+    let status = new_session.status();
+    *session.lock().unwrap() = Some(new_session);
+    info!("Eye calibration of {} started", device_type);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn get_eye_calibration_status(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<Option<EyeCalibrationStatus>, String> {
+    let session = eye_calibration_session(&app, device_type)?;
+    let status = session.lock().unwrap().as_ref().map(|session| session.status());
+    Ok(status)
+}
+
+// 环视结束，进入注视中心
+#[tauri::command]
+pub fn next_eye_calibration_step(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<EyeCalibrationStatus, String> {
+    let session = eye_calibration_session(&app, device_type)?;
+    let mut session = session.lock().unwrap();
+    let session = session.as_mut().ok_or_else(|| "校准未开始".to_string())?;
+    session.next_step()?;
+    Ok(session.status())
+}
+
+// 计算并保存校准结果，失败时保留会话以便继续采集
+#[tauri::command]
+pub fn finish_eye_calibration(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<EyeCalibration, String> {
+    let session = eye_calibration_session(&app, device_type)?;
+    let calibration = {
+        let mut session = session.lock().unwrap();
+        let calibration = session.as_ref().ok_or_else(|| "校准未开始".to_string())?.finish()?;
+        *session = None;
+        calibration
+    };
+    {
+        let mut eye_config = EYE_CONFIG.write().unwrap();
+        calibration.store(&mut eye_config.params, device_type);
+        eye_config.modified = true;
+    }
+    info!("Eye calibration of {} finished: {:?}", device_type, calibration);
+    write_eye_config().map_err(|e| format!("保存眼追配置失败: {}", e))?;
+    Ok(calibration)
+}
+
+#[tauri::command]
+pub fn cancel_eye_calibration(
+    app: tauri::AppHandle<impl Runtime>,
+    device_type: i32
+) -> Result<(), String> {
+    let session = eye_calibration_session(&app, device_type)?;
+    *session.lock().unwrap() = None;
+    info!("Eye calibration of {} cancelled", device_type);
+    Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod interface;
-pub mod init;
\ No newline at end of file
+pub mod init;
+pub mod preview;
+pub mod transport;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::{borrow::Cow, collections::HashMap, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex, RwLock}};
+use crate::websocket::image_msg::PreviewSettings;
+use tauri::{http::{response::Builder, Request, Response, StatusCode}, AppHandle, Manager, Runtime};
+use ftlog::*;
+
+// 预览图通过 papertracker:// 协议以原始 JPEG 提供给前端，Channel 只传元数据
+pub const PREVIEW_PROTOCOL: &str = "papertracker";
+
+#[derive(Clone)]
+struct PreviewFrame {
+    seq: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+// 一个窗口对一路图像流的预览订阅
+struct PreviewSession {
+    device: String,
+    window: String,
+    stop: Arc<AtomicBool>,
+    settings: Arc<RwLock<PreviewSettings>>,
+}
+
+#[derive(Default)]
+pub struct PreviewState {
+    // 按会话句柄保存的最新帧
+    frames: RwLock<HashMap<u64, PreviewFrame>>,
+    // 按设备保存的最新帧，不区分是哪个会话发布的，各窗口预览设置不同时为最后编码的那一份
+    devices: RwLock<HashMap<String, PreviewFrame>>,
+    sessions: Mutex<HashMap<u64, PreviewSession>>,
+    next_handle: AtomicU64,
+}
+
+impl PreviewState {
+    // 注册新的预览会话，同一窗口同一设备的旧会话会被停止（窗口刷新后重新订阅的情况）
+    pub fn register(&self, device: &str, window: &str, settings: PreviewSettings) -> (u64, Arc<AtomicBool>, Arc<RwLock<PreviewSettings>>) {
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed);
+        let stop = Arc::new(AtomicBool::new(false));
+        let settings = Arc::new(RwLock::new(settings));
+        let mut sessions = self.sessions.lock().unwrap();
+        for session in sessions.values() {
+            if session.device == device && session.window == window {
+                session.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        sessions.insert(handle, PreviewSession {
+            device: device.to_string(),
+            window: window.to_string(),
+            stop: stop.clone(),
+            settings: settings.clone(),
+        });
+        (handle, stop, settings)
+    }
+
+    pub fn update_settings(&self, handle: u64, settings: PreviewSettings) -> bool {
+        match self.sessions.lock().unwrap().get(&handle) {
+            Some(session) => {
+                *session.settings.write().unwrap() = settings;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stop(&self, handle: u64) -> bool {
+        match self.sessions.lock().unwrap().get(&handle) {
+            Some(session) => {
+                session.stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stop_window(&self, window: &str) -> usize {
+        let sessions = self.sessions.lock().unwrap();
+        let mut stopped = 0;
+        for session in sessions.values().filter(|session| session.window == window) {
+            session.stop.store(true, Ordering::Relaxed);
+            stopped += 1;
+        }
+        stopped
+    }
+
+    // 预览线程退出时清理会话和缓存的帧，设备没有其他会话时一并清理设备的帧
+    pub fn finish(&self, handle: u64) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.remove(&handle) {
+            if !sessions.values().any(|other| other.device == session.device) {
+                self.devices.write().unwrap().remove(&session.device);
+            }
+        }
+        self.frames.write().unwrap().remove(&handle);
+    }
+
+    // 更新某个会话和对应设备的最新帧，返回会话的帧序号供前端拼接 URL 防止缓存
+    pub fn publish(&self, handle: u64, device: &str, jpeg: Arc<Vec<u8>>) -> u64 {
+        let seq = {
+            let mut frames = self.frames.write().unwrap();
+            let seq = frames.get(&handle).map(|frame| frame.seq + 1).unwrap_or(0);
+            frames.insert(handle, PreviewFrame { seq, jpeg: jpeg.clone() });
+            seq
+        };
+        let mut devices = self.devices.write().unwrap();
+        let device_seq = devices.get(device).map(|frame| frame.seq + 1).unwrap_or(0);
+        devices.insert(device.to_string(), PreviewFrame { seq: device_seq, jpeg });
+        seq
+    }
+
+    pub fn latest(&self, handle: u64) -> Option<Arc<Vec<u8>>> {
+        self.frames.read().unwrap().get(&handle).map(|frame| frame.jpeg.clone())
+    }
+
+    pub fn latest_for_device(&self, device: &str) -> Option<Arc<Vec<u8>>> {
+        self.devices.read().unwrap().get(device).map(|frame| frame.jpeg.clone())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PreviewRoute {
+    Session(u64),
+    Device(String),
+}
+
+fn parse_route(path: &str) -> Option<PreviewRoute> {
+    let path = path.trim_matches('/');
+    match path.strip_prefix("device/") {
+        Some(device) if !device.is_empty() => Some(PreviewRoute::Device(device.to_string())),
+        Some(_) => None,
+        None => path.parse().ok().map(PreviewRoute::Session),
+    }
+}
+
+// papertracker://localhost/<handle>?seq=N 为某个预览会话的最新帧
+// papertracker://localhost/device/<device>?seq=N 为某路设备的最新帧
+pub fn handle_preview_request<R: Runtime>(app: &AppHandle<R>, request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let frame = app.try_state::<PreviewState>().and_then(|state| match parse_route(request.uri().path())? {
+        PreviewRoute::Session(handle) => state.latest(handle),
+        PreviewRoute::Device(device) => state.latest_for_device(&device),
+    });
+    match frame {
+        Some(jpeg) => build_response(
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", "image/jpeg")
+                .header("Cache-Control", "no-store")
+                .header("Access-Control-Allow-Origin", "*"),
+            Cow::Owned(jpeg.as_ref().clone()),
+        ),
+        None => build_response(
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header("Access-Control-Allow-Origin", "*"),
+            Cow::Borrowed(&[][..]),
+        ),
+    }
+}
+
+// 构造响应失败时返回空的 500，协议处理中不能 panic
+fn build_response(builder: Builder, body: Cow<'static, [u8]>) -> Response<Cow<'static, [u8]>> {
+    builder.body(body).unwrap_or_else(|e| {
+        error!("Failed to build preview response: {}", e);
+        let mut response = Response::new(Cow::Borrowed(&[][..]));
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(byte: u8) -> Arc<Vec<u8>> {
+        Arc::new(vec![byte])
+    }
+
+    #[test]
+    fn parses_session_and_device_routes() {
+        assert_eq!(parse_route("/3"), Some(PreviewRoute::Session(3)));
+        assert_eq!(parse_route("/device/left_eye"), Some(PreviewRoute::Device("left_eye".to_string())));
+        assert_eq!(parse_route("/device/"), None);
+        assert_eq!(parse_route("/face"), None);
+    }
+
+    #[test]
+    fn device_route_serves_latest_frame_of_any_session() {
+        let state = PreviewState::default();
+        let (first, _, _) = state.register("face", "main", PreviewSettings::default());
+        let (second, _, _) = state.register("face", "settings", PreviewSettings::default());
+        assert_eq!(state.publish(first, "face", jpeg(1)), 0);
+        assert_eq!(state.publish(second, "face", jpeg(2)), 0);
+        assert_eq!(state.publish(first, "face", jpeg(3)), 1);
+        assert_eq!(*state.latest(first).unwrap(), vec![3]);
+        assert_eq!(*state.latest(second).unwrap(), vec![2]);
+        assert_eq!(*state.latest_for_device("face").unwrap(), vec![3]);
+        assert!(state.latest_for_device("left_eye").is_none());
+    }
+
+    #[test]
+    fn finish_keeps_device_frame_while_other_sessions_run() {
+        let state = PreviewState::default();
+        let (first, _, _) = state.register("face", "main", PreviewSettings::default());
+        let (second, _, _) = state.register("face", "settings", PreviewSettings::default());
+        state.publish(first, "face", jpeg(1));
+        state.finish(first);
+        assert!(state.latest(first).is_none());
+        assert!(state.latest_for_device("face").is_some());
+        state.finish(second);
+        assert!(state.latest_for_device("face").is_none());
+    }
+
+    #[test]
+    fn register_replaces_session_of_same_window() {
+        let state = PreviewState::default();
+        let (_, old_stop, _) = state.register("face", "main", PreviewSettings::default());
+        let (_, other_stop, _) = state.register("left_eye", "main", PreviewSettings::default());
+        let (_, new_stop, _) = state.register("face", "main", PreviewSettings::default());
+        assert!(old_stop.load(Ordering::Relaxed));
+        assert!(!other_stop.load(Ordering::Relaxed));
+        assert!(!new_stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn update_settings_reaches_running_session() {
+        let state = PreviewState::default();
+        let (handle, _, settings) = state.register("face", "main", PreviewSettings::default());
+        let updated = PreviewSettings { max_width: 160, jpeg_quality: 50, fps: 10.0 };
+        assert!(state.update_settings(handle, updated.clone()));
+        assert_eq!(*settings.read().unwrap(), updated);
+        assert!(!state.update_settings(handle + 1, updated));
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::Ordering;
+use crossbeam::channel::Sender;
+use tauri::{AppHandle, Manager, Runtime};
+use crate::{utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE, DEVICE_TYPE_UNKNOWN}, websocket::image_msg::StreamSettingRequest};
+use super::init::{ImageStreamState, SerialState};
+
+// 控制消息的发送通道，插着数据线时优先走串口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlTransport {
+    Serial,
+    Wifi,
+}
+
+pub fn get_setting_req(state: &ImageStreamState, device_type: i32) -> Result<Sender<StreamSettingRequest>, String> {
+    match device_type {
+        DEVICE_TYPE_FACE => Ok(state.face_setting_req.clone()),
+        DEVICE_TYPE_LEFT_EYE => Ok(state.left_eye_setting_req.clone()),
+        DEVICE_TYPE_RIGHT_EYE => Ok(state.right_eye_setting_req.clone()),
+        _ => Err("Invalid device type".to_string()),
+    }
+}
+
+fn is_wifi_connected(state: &ImageStreamState, device_type: i32) -> bool {
+    match device_type {
+        DEVICE_TYPE_FACE => state.face_wifi_connected.load(Ordering::Relaxed),
+        DEVICE_TYPE_LEFT_EYE => state.left_eye_wifi_connected.load(Ordering::Relaxed),
+        DEVICE_TYPE_RIGHT_EYE => state.right_eye_wifi_connected.load(Ordering::Relaxed),
+        _ => false,
+    }
+}
+
+// 串口当前连接的设备类型，未连接返回 None；读取串口线程缓存的值，不做任何 IO
+fn serial_device_type<R: Runtime>(app: &AppHandle<R>) -> Option<i32> {
+    match app.state::<SerialState>().device_type.load(Ordering::Relaxed) {
+        DEVICE_TYPE_UNKNOWN => None,
+        device_type => Some(device_type),
+    }
+}
+
+pub fn select_transport<R: Runtime>(app: &AppHandle<R>, device_type: i32) -> Result<ControlTransport, String> {
+    if serial_device_type(app) == Some(device_type) {
+        return Ok(ControlTransport::Serial);
+    }
+    if is_wifi_connected(&app.state::<ImageStreamState>(), device_type) {
+        return Ok(ControlTransport::Wifi);
+    }
+    Err("设备未连接，请检查数据线或WIFI".to_string())
+}
@@ -6,6 +6,10 @@ mod websocket;
 mod integration;
 mod algorithm;
 
+// 只在 bench feature 下导出，供 benches 使用
+#[cfg(feature = "bench")]
+pub use websocket::image_msg::Frame;
+
 use paper_tracker_config::config::init_config;
 use updater::version_check::check_for_updates;
 use ftlog::*;
@@ -18,14 +22,50 @@ use integration::interface::{
     start_right_eye_image_stream,
     set_brightness,
     set_rotation,
+    set_flip,
+    set_preprocess_pipeline,
+    set_preprocess_stage_enabled,
+    stop_image_stream,
+    stop_window_image_streams,
+    set_preview_settings,
+    set_energy_mode,
+    restart_device,
+    query_device_status,
+    set_device_ip,
+    get_stream_stats,
+    set_stereo_tolerance,
+    get_stereo_stats,
+    get_latency_stats,
+    set_face_tracking,
+    set_eye_tracking,
+    set_eye_detector,
+    set_pupil_detector_config,
+    set_use_filter,
+    get_use_filter,
+    set_filter_kind,
+    set_one_euro_params,
+    update_calibration,
+    get_calibration,
+    set_response_curve,
+    get_response_curves,
+    start_eye_calibration,
+    get_eye_calibration_status,
+    next_eye_calibration_step,
+    finish_eye_calibration,
+    cancel_eye_calibration,
 };
 use integration::init::init_device;
+use integration::preview::{handle_preview_request, PreviewState, PREVIEW_PROTOCOL};
+use tauri::Manager;
 
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .register_uri_scheme_protocol(PREVIEW_PROTOCOL, |ctx, request| {
+            handle_preview_request(ctx.app_handle(), request)
+        })
         .setup(|app| {
             // build logger
             ftlog::Builder::new()
@@ -43,6 +83,14 @@ pub fn run() {
             info!("Application initialized successfully");
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // 窗口关闭时停止它的所有预览订阅
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Some(state) = window.try_state::<PreviewState>() {
+                    state.stop_window(window.label());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             check_for_updates,
             restart_esp32,
@@ -53,6 +101,37 @@ pub fn run() {
             start_right_eye_image_stream,
             set_brightness,
             set_rotation,
+            set_flip,
+            set_preprocess_pipeline,
+            set_preprocess_stage_enabled,
+            stop_image_stream,
+            stop_window_image_streams,
+            set_preview_settings,
+            set_energy_mode,
+            restart_device,
+            query_device_status,
+            set_device_ip,
+            get_stream_stats,
+            set_stereo_tolerance,
+            get_stereo_stats,
+            get_latency_stats,
+            set_face_tracking,
+            set_eye_tracking,
+            set_eye_detector,
+            set_pupil_detector_config,
+            set_use_filter,
+            get_use_filter,
+            set_filter_kind,
+            set_one_euro_params,
+            update_calibration,
+            get_calibration,
+            set_response_curve,
+            get_response_curves,
+            start_eye_calibration,
+            get_eye_calibration_status,
+            next_eye_calibration_step,
+            finish_eye_calibration,
+            cancel_eye_calibration,
             ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,7 +1,11 @@
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
+use std::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager, Runtime};
+use crate::algorithm::{expression::Expression, eye_tracker::EyeDetectorKind, filter::FilterKind, one_euro_filter::OneEuroParams, pupil_detector::PupilDetectorConfig, response_curve::ResponseCurve};
 use crate::utils::roi::Roi;
+use crate::websocket::{frame_source::FrameSourceConfig, preprocess::PreprocessStage};
 use config;
 use toml;
 use once_cell::sync::{Lazy, OnceCell};
@@ -28,6 +32,67 @@ pub struct EyeFunctionalConfig {
     pub flip_y: bool,
     pub left_rotate_angle: i32,
     pub right_rotate_angle: i32,    
+
+    // 图像预处理链
+    #[serde(default)]
+    pub left_preprocess: Vec<PreprocessStage>,
+    #[serde(default)]
+    pub right_preprocess: Vec<PreprocessStage>,
+
+    // 图像来源
+    #[serde(default)]
+    pub left_source: FrameSourceConfig,
+    #[serde(default)]
+    pub right_source: FrameSourceConfig,
+
+    // 左右眼帧配对允许的最大时间差，单位毫秒
+    #[serde(default = "default_stereo_tolerance_ms")]
+    pub stereo_tolerance_ms: f64,
+
+    // 推理线程数
+    #[serde(default = "default_inference_threads")]
+    pub inference_threads: usize,
+
+    // 检测方式
+    #[serde(default)]
+    pub left_detector: EyeDetectorKind,
+    #[serde(default)]
+    pub right_detector: EyeDetectorKind,
+    #[serde(default)]
+    pub pupil_detector: PupilDetectorConfig,
+
+    // 是否运行眼追，关闭后不再订阅眼部图像
+    #[serde(default = "default_eye_tracking")]
+    pub tracking: bool,
+
+    // 检测置信度低于该值时保持上一次的视线
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f32,
+
+    // 眼追滤波开关，与面捕的开关分开，滤波参数仍使用面捕配置
+    #[serde(default)]
+    pub use_filter: bool,
+    #[serde(default)]
+    pub gaze_filter: FilterKind,
+    // 睁眼程度默认不滤波，避免眨眼延迟
+    #[serde(default = "default_openness_filter")]
+    pub openness_filter: FilterKind,
+}
+
+fn default_eye_tracking() -> bool {
+    true
+}
+
+fn default_min_confidence() -> f32 {
+    0.3
+}
+
+fn default_openness_filter() -> FilterKind {
+    FilterKind::None
+}
+
+fn default_stereo_tolerance_ms() -> f64 {
+    20.0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,7 +141,7 @@ impl EyeConfig {
     }
 
     pub fn write() -> Result<()> {
-        let toml_string = toml::to_string(&*EYE_CONFIG).unwrap();
+        let toml_string = toml::to_string(&*EYE_CONFIG.read().unwrap()).unwrap();
         // 检查文件是否已经存在
         if std::path::Path::new(EYE_CONFIG_PATH.get().unwrap()).exists() {
             // 如果存在，先删除
@@ -105,32 +170,124 @@ pub struct FaceFunctionalConfig {
     pub rect: Roi,
     pub use_filter: bool,
     pub wifi_ip: String,
+
+    // 图像预处理链
+    #[serde(default)]
+    pub preprocess: Vec<PreprocessStage>,
+
+    // 图像来源
+    #[serde(default)]
+    pub source: FrameSourceConfig,
+
+    // 推理线程数
+    #[serde(default = "default_inference_threads")]
+    pub inference_threads: usize,
+
+    // 是否运行表情识别，关闭后不再订阅面捕图像
+    #[serde(default = "default_face_tracking")]
+    pub tracking: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_inference_threads() -> usize {
+    2
+}
+
+fn default_face_tracking() -> bool {
+    true
+}
+
+// 单个表情的偏移、增益和响应曲线
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExpressionParams {
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default = "default_expression_gain")]
+    pub gain: f64,
+    #[serde(default)]
+    pub curve: ResponseCurve,
+}
+
+fn default_expression_gain() -> f64 {
+    1.0
+}
+
+impl Default for ExpressionParams {
+    fn default() -> Self {
+        ExpressionParams {
+            offset: 0.0,
+            gain: default_expression_gain(),
+            curve: ResponseCurve::default(),
+        }
+    }
+}
+
+impl ExpressionParams {
+    // 先减去偏移再乘以增益，限制在 [0, 1] 后经过响应曲线
+    pub fn apply(&self, value: f64) -> f64 {
+        self.curve.apply(((value - self.offset) * self.gain).clamp(0.0, 1.0))
+    }
+}
+
+// 标定时修改的字段
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationField {
+    #[default]
+    Gain,
+    Offset,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FaceParamsConfig {
-    pub cheek_puff_left_offset: f64,
-    pub cheek_puff_right_offset: f64,
-    pub jaw_open_offset: f64,
-    pub tongue_out_offset: f64,
-    pub mouth_close_offset: f64,
-    pub mouth_funnel_offset: f64,
-    pub mouth_pucker_offset: f64,
-    pub mouth_roll_upper_offset: f64,
-    pub mouth_roll_lower_offset: f64,
-    pub mouth_shrug_upper_offset: f64,
-    pub mouth_shrug_lower_offset: f64,
-    pub cheek_puff_left_gain: f64,
-    pub cheek_puff_right_gain: f64,
-    pub jaw_open_gain: f64,
-    pub tongue_out_gain: f64,
-    pub mouth_close_gain: f64,
-    pub mouth_funnel_gain: f64,
-    pub mouth_pucker_gain: f64,
-    pub mouth_roll_upper_gain: f64,
-    pub mouth_roll_lower_gain: f64,
-    pub mouth_shrug_upper_gain: f64,
-    pub mouth_shrug_lower_gain: f64,
+    // 按表情保存，没有的表情使用默认值
+    #[serde(default)]
+    pub expressions: BTreeMap<Expression, ExpressionParams>,
+    // 旧版配置每个表情两个字段（如 jaw_open_offset / jaw_open_gain），读取后迁移到 expressions
+    #[serde(flatten)]
+    legacy: HashMap<String, f64>,
+}
+
+impl FaceParamsConfig {
+    pub fn get(&self, expression: Expression) -> ExpressionParams {
+        self.expressions.get(&expression).cloned().unwrap_or_default()
+    }
+
+    // 手动编辑的配置可能带有无效的曲线，回退为线性
+    fn reset_invalid_curves(&mut self) {
+        for (expression, params) in self.expressions.iter_mut() {
+            if let Err(e) = params.curve.validate() {
+                warn!("Invalid response curve for {}, using linear: {}", expression.id(), e);
+                params.curve = ResponseCurve::Linear;
+            }
+        }
+    }
+
+    fn migrate_legacy(&mut self) {
+        for (key, value) in self.legacy.drain() {
+            let (name, is_gain) = if let Some(name) = key.strip_suffix("_offset") {
+                (name, false)
+            } else if let Some(name) = key.strip_suffix("_gain") {
+                (name, true)
+            } else {
+                warn!("Unknown face param: {}", key);
+                continue;
+            };
+            let expression = match Expression::from_id(name) {
+                Some(expression) => expression,
+                None => {
+                    warn!("Unknown face param: {}", key);
+                    continue;
+                }
+            };
+            let params = self.expressions.entry(expression).or_default();
+            if is_gain {
+                // 旧版默认配置的增益为 0，表示未设置
+                params.gain = if value == 0.0 { default_expression_gain() } else { value };
+            } else {
+                params.offset = value;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,11 +295,20 @@ pub struct FaceFilterConfig {
     pub kalman_dt: f64,
     pub kalman_q_factor: f64,
     pub r_factor: f64,
+
+    #[serde(default)]
+    pub one_euro: OneEuroParams,
+    // 没有单独设置的表情使用的滤波方式
+    #[serde(default)]
+    pub default_filter: FilterKind,
+    #[serde(default)]
+    pub expressions: BTreeMap<Expression, FilterKind>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FaceConfig {
     pub functional: FaceFunctionalConfig,
+    #[serde(default)]
     pub params: FaceParamsConfig,
     pub filter: FaceFilterConfig,
     pub modified: bool,
@@ -152,7 +318,9 @@ impl FaceConfig {
     pub fn new(config_path: &str) -> Result<Self> {
         let mut settigns = config::Config::default();
         settigns.merge(config::File::with_name(config_path))?;
-        let conf: FaceConfig = settigns.try_into()?;
+        let mut conf: FaceConfig = settigns.try_into()?;
+        conf.params.migrate_legacy();
+        conf.params.reset_invalid_curves();
         Ok(conf)
     }
 
@@ -162,14 +330,14 @@ impl FaceConfig {
     }
 
     pub fn write() -> Result<()> {
-        let toml_string = toml::to_string(&*FACE_CONFIG).unwrap();
+        let toml_string = toml::to_string(&*FACE_CONFIG.read().unwrap()).unwrap();
         // 检查文件是否已经存在
         if std::path::Path::new(FACE_CONFIG_PATH.get().unwrap()).exists() {
             // 如果存在，先删除
             std::fs::remove_file(FACE_CONFIG_PATH.get().unwrap())?;
         }
         // 创建文件并写入内容
-        let mut file = std::fs::File::create(EYE_CONFIG_PATH.get().unwrap())?;
+        let mut file = std::fs::File::create(FACE_CONFIG_PATH.get().unwrap())?;
         file.write_all(toml_string.as_bytes())?;
         // 刷新文件缓冲区
         file.flush()?;
@@ -185,10 +353,11 @@ pub static EYE_CONFIG_PATH: OnceCell<String> = OnceCell::new();
 pub static FACE_CONFIG_PATH: OnceCell<String> = OnceCell::new();
 
 // 配置文件加载失败可直接panic
+// 运行时修改配置需要先获取写锁，修改后调用 write_*_config 持久化
 
-pub static EYE_CONFIG: Lazy<EyeConfig> = Lazy::new(|| EyeConfig::new_args().unwrap());
+pub static EYE_CONFIG: Lazy<RwLock<EyeConfig>> = Lazy::new(|| RwLock::new(EyeConfig::new_args().unwrap()));
 
-pub static FACE_CONFIG: Lazy<FaceConfig> = Lazy::new(|| FaceConfig::new_args().unwrap());
+pub static FACE_CONFIG: Lazy<RwLock<FaceConfig>> = Lazy::new(|| RwLock::new(FaceConfig::new_args().unwrap()));
 
 pub fn write_eye_config() -> Result<()> {
     EyeConfig::write()
@@ -223,4 +392,140 @@ pub fn init_config<R: Runtime>(app: &AppHandle<R>) -> Result<()> {
         face_path.unwrap().to_str().unwrap().to_string(),
     );
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 旧版 face_config.toml，params 为每个表情一对 offset / gain 字段
+    const LEGACY_FACE_CONFIG: &str = r#"
+modified = false
+
+[functional]
+brightness=0
+rotate_angle=0
+energy_mode=0
+use_filter=false
+wifi_ip="0.0.0.0"
+
+[functional.rect]
+x=0
+y=0
+width=1
+height=1
+
+[params]
+cheek_puff_left_offset=0
+jaw_open_offset=0.1
+mouth_close_offset=0
+cheek_puff_left_gain=0
+jaw_open_gain=0
+mouth_close_gain=1.5
+cheekRight_offset=0.2
+cheekRight_gain=2.0
+unknown_gain=3.0
+jaw_open=1.0
+
+[filter]
+kalman_dt=0
+kalman_q_factor=0
+r_factor=0
+"#;
+
+    fn params(offset: f64, gain: f64) -> ExpressionParams {
+        ExpressionParams { offset, gain, curve: ResponseCurve::Linear }
+    }
+
+    // 通过 FaceConfig::new 读取，与实际加载一样经过配置库（键名会被转为小写）
+    fn load_face_config(name: &str, text: &str) -> FaceConfig {
+        let path = std::env::temp_dir().join(format!("papertracker_{}_{}.toml", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let config = FaceConfig::new(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        config.unwrap()
+    }
+
+    #[test]
+    fn migrates_legacy_face_config() {
+        let config = load_face_config("legacy_face_config", LEGACY_FACE_CONFIG);
+        let expressions = &config.params.expressions;
+        // 增益 0 表示旧版未设置，迁移为 1
+        assert_eq!(expressions[&Expression::CheekPuffLeft], params(0.0, 1.0));
+        assert_eq!(expressions[&Expression::JawOpen], params(0.1, 1.0));
+        assert_eq!(expressions[&Expression::MouthClose], params(0.0, 1.5));
+        assert!(config.params.legacy.is_empty());
+        assert!(config.functional.tracking);
+    }
+
+    #[test]
+    fn migrates_ui_alias_and_skips_unknown_keys() {
+        let config = load_face_config("face_config_alias", LEGACY_FACE_CONFIG);
+        // cheekRight_* 经过配置库后变成 cheekright_*，仍然能识别
+        assert_eq!(config.params.get(Expression::CheekPuffRight), params(0.2, 2.0));
+        // unknown_gain 和 jaw_open 被忽略
+        assert_eq!(config.params.expressions.len(), 4);
+    }
+
+    #[test]
+    fn face_params_toml_round_trip() {
+        let mut config = FaceParamsConfig::default();
+        config.expressions.insert(Expression::JawOpen, params(0.1, 1.5));
+        config.expressions.insert(Expression::CheekPuffLeft, params(0.0, 2.0));
+        let text = toml::to_string(&config).unwrap();
+        // 新格式不再写出旧版字段
+        assert!(!text.contains("_gain"));
+        let mut parsed: FaceParamsConfig = toml::from_str(&text).unwrap();
+        parsed.migrate_legacy();
+        assert_eq!(parsed.expressions, config.expressions);
+    }
+
+    #[test]
+    fn expression_params_apply_offset_then_gain() {
+        let default = ExpressionParams::default();
+        assert_eq!(default.apply(0.4), 0.4);
+        assert_eq!(default.apply(1.5), 1.0);
+        assert_eq!(default.apply(-0.2), 0.0);
+
+        let scaled = params(0.1, 2.0);
+        assert!((scaled.apply(0.3) - 0.4).abs() < 1e-12);
+        // 低于偏移输出 0，放大后超过 1 时限幅
+        assert_eq!(scaled.apply(0.05), 0.0);
+        assert_eq!(scaled.apply(0.8), 1.0);
+        // 增益为 0 时输出恒为 0
+        assert_eq!(params(0.0, 0.0).apply(0.7), 0.0);
+    }
+
+    #[test]
+    fn expression_params_apply_curve_after_gain() {
+        let curved = ExpressionParams {
+            offset: 0.1,
+            gain: 2.0,
+            curve: ResponseCurve::Power { exponent: 2.0, deadzone: 0.0, saturation: 1.0 },
+        };
+        // (0.35 - 0.1) * 2 = 0.5，再平方
+        assert!((curved.apply(0.35) - 0.25).abs() < 1e-12);
+        assert_eq!(curved.apply(0.9), 1.0);
+    }
+
+    #[test]
+    fn invalid_curves_fall_back_to_linear() {
+        let text = r#"
+[expressions.jaw_open]
+gain = 1.5
+[expressions.jaw_open.curve]
+type = "power"
+exponent = 2.0
+deadzone = 0.6
+saturation = 0.4
+
+[expressions.tongue_out.curve]
+type = "piecewise"
+points = [[0.0, 0.0], [0.5, 0.5], [1.0, 1.0]]
+"#;
+        let mut config: FaceParamsConfig = toml::from_str(text).unwrap();
+        config.reset_invalid_curves();
+        assert_eq!(config.get(Expression::JawOpen), params(0.0, 1.5));
+        assert_eq!(config.get(Expression::TongueOut).curve, ResponseCurve::Piecewise { points: vec![[0.0, 0.0], [0.5, 0.5], [1.0, 1.0]] });
+    }
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, vec};
+use std::{collections::HashMap, sync::{atomic::{AtomicI32, Ordering}, Arc}, vec};
 use crossbeam::channel::{Sender, Receiver};
 use ftlog::*;
 use regex::Regex;
 use serialport::SerialPort;
-use crate::{integration::interface::StreamEvent, utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE}};
+use crate::{integration::interface::StreamEvent, utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE, DEVICE_TYPE_UNKNOWN}};
 
 use super::{esp32_control::{find_esp32_port, flash_esp32, restart_esp32}, serial_msg::{DeviceStatus, PortState, SerialMessage, SerialRequest, SerialResponse, SerialSendPacket, WifiError}};
 use tauri::{AppHandle, Emitter, EventTarget, Runtime};
@@ -28,6 +28,8 @@ pub struct Esp32Serial<R: Runtime> {
     last_message: HashMap<i32, SerialMessage>,
     // 
     serial_info: (String, i32),
+    // 串口上设备的类型，由状态包更新，未连接时为 DEVICE_TYPE_UNKNOWN，供控制命令无阻塞读取
+    device_type: Arc<AtomicI32>,
     // 
     run: bool,
     // 
@@ -50,6 +52,7 @@ impl<R: Runtime> Esp32Serial<R> {
             port_state: PortState::Disconnected,
             last_message: HashMap::new(),
             serial_info: ("".to_string(), 0),
+            device_type: Arc::new(AtomicI32::new(DEVICE_TYPE_UNKNOWN)),
             run: false,
             app_handle: app,
         }
@@ -71,12 +74,19 @@ impl<R: Runtime> Esp32Serial<R> {
         self.write_tx.clone()
     }
 
+    pub fn get_device_type(&self) -> Arc<AtomicI32> {
+        self.device_type.clone()
+    }
+
     pub fn start(&mut self) {
         let mut port : Option<Box<dyn SerialPort + 'static>> = None;
         self.run = true;
         loop {
             // Check if the port state has changed
             if let PortState::Disconnected = self.port_state {
+                // 断开后清除缓存的设备类型，重新连接后等待新的状态包
+                self.device_type.store(DEVICE_TYPE_UNKNOWN, Ordering::Relaxed);
+                self.last_message.remove(&5);
                 if let Err(e) = self.app_handle.emit("face_serial_status", "面捕设备未连接") {
                     error!("Failed to emit serial status event: {}", e);
                 }
@@ -270,6 +280,12 @@ impl<R: Runtime> Esp32Serial<R> {
             SerialRequest::GetStatus => {
                 self.response_tx.broadcast(SerialResponse::Status((self.port_state.clone(), self.serial_info.1)));
             }
+            SerialRequest::ResendDeviceStatus => {
+                // 固件没有查询命令，重新发布最近一次收到的状态包
+                if let Some(message) = self.last_message.get(&5) {
+                    let _ = self.message_tx.try_broadcast(message.clone());
+                }
+            }
             SerialRequest::Stop => {
                 self.run = false;
             }
@@ -391,6 +407,7 @@ impl<R: Runtime> Esp32Serial<R> {
                         .collect::<Vec<_>>();
                     let ip = ip_parts.join(".");
                     self.serial_info.1 = version;
+                    self.device_type.store(version, Ordering::Relaxed);
                     let _ = self.message_tx.try_broadcast(
                         SerialMessage::DeviceStatus(
                             DeviceStatus {
@@ -24,6 +24,8 @@ pub enum SerialRequest {
     // tool path, firmware path
     Flash(FlashCommand),
     GetStatus,
+    // 重新发布最近一次的设备状态
+    ResendDeviceStatus,
     Stop,
     Start
 }
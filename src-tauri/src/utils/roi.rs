@@ -1,3 +1,4 @@
+use opencv::core::Rect;
 use serde::{Deserialize, Serialize};
 
 
@@ -7,4 +8,62 @@ pub struct Roi {
     pub y: f32,
     pub width: f32,
     pub height: f32,
-}
\ No newline at end of file
+}
+
+impl Roi {
+    // 坐标都不超过 1 时按图像比例处理，否则按像素处理；结果裁剪到图像范围内，无效时返回整幅图像
+    // 因此 {0, 0, 1, 1} 总是表示整幅图像（默认配置），而不是左上角 1x1 像素，像素 ROI 至少要有一边超过 1
+    pub fn to_rect(&self, cols: i32, rows: i32) -> Rect {
+        let normalized = self.x <= 1.0 && self.y <= 1.0 && self.width <= 1.0 && self.height <= 1.0;
+        let (scale_x, scale_y) = if normalized { (cols as f32, rows as f32) } else { (1.0, 1.0) };
+        let x = ((self.x * scale_x).round() as i32).clamp(0, cols);
+        let y = ((self.y * scale_y).round() as i32).clamp(0, rows);
+        let width = ((self.width * scale_x).round() as i32).min(cols - x);
+        let height = ((self.height * scale_y).round() as i32).min(rows - y);
+        if width <= 0 || height <= 0 {
+            return Rect::new(0, 0, cols, rows);
+        }
+        Rect::new(x, y, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roi(x: f32, y: f32, width: f32, height: f32) -> Roi {
+        Roi { x, y, width, height }
+    }
+
+    #[test]
+    fn normalized_roi_scales_to_image() {
+        assert_eq!(roi(0.25, 0.5, 0.5, 0.25).to_rect(400, 200), Rect::new(100, 100, 200, 50));
+    }
+
+    #[test]
+    fn unit_roi_is_whole_image() {
+        assert_eq!(roi(0.0, 0.0, 1.0, 1.0).to_rect(640, 480), Rect::new(0, 0, 640, 480));
+        // 同样的数值不会被当作 1x1 像素
+        assert_eq!(roi(1.0, 1.0, 1.0, 1.0).to_rect(640, 480), Rect::new(0, 0, 640, 480));
+    }
+
+    #[test]
+    fn pixel_roi_is_used_as_is() {
+        assert_eq!(roi(10.0, 20.0, 100.0, 50.0).to_rect(640, 480), Rect::new(10, 20, 100, 50));
+        // 任意一个值超过 1 就整体按像素处理
+        assert_eq!(roi(0.0, 0.0, 1.0, 2.0).to_rect(640, 480), Rect::new(0, 0, 1, 2));
+    }
+
+    #[test]
+    fn roi_is_clamped_to_image() {
+        assert_eq!(roi(600.0, 400.0, 100.0, 100.0).to_rect(640, 480), Rect::new(600, 400, 40, 80));
+        assert_eq!(roi(0.5, 0.5, 1.0, 1.0).to_rect(100, 100), Rect::new(50, 50, 50, 50));
+    }
+
+    #[test]
+    fn empty_roi_falls_back_to_whole_image() {
+        assert_eq!(roi(0.0, 0.0, 0.0, 0.0).to_rect(640, 480), Rect::new(0, 0, 640, 480));
+        assert_eq!(roi(700.0, 10.0, 50.0, 50.0).to_rect(640, 480), Rect::new(0, 0, 640, 480));
+        assert_eq!(roi(-5.0, -5.0, -1.0, 0.5).to_rect(640, 480), Rect::new(0, 0, 640, 480));
+    }
+}
@@ -0,0 +1,86 @@
+use ftlog::*;
+use opencv::{core::{Mat, MatTraitConst}, videoio::{self, VideoCapture, VideoCaptureTrait, VideoCaptureTraitConst}};
+use super::frame_source::{FrameSource, SourceEvent};
+
+// 本地 USB 红外摄像头，Linux 下走 V4L2
+pub struct CameraSource {
+    index: i32,
+    width: i32,
+    height: i32,
+    fps: f64,
+    capture: Option<VideoCapture>,
+}
+
+impl CameraSource {
+    pub fn new(index: i32, width: i32, height: i32, fps: f64) -> Self {
+        CameraSource {
+            index,
+            width,
+            height,
+            fps,
+            capture: None,
+        }
+    }
+
+    fn open(&self) -> opencv::Result<VideoCapture> {
+        #[cfg(target_os = "linux")]
+        let api = videoio::CAP_V4L2;
+        #[cfg(not(target_os = "linux"))]
+        let api = videoio::CAP_ANY;
+        let mut capture = VideoCapture::new(self.index, api)?;
+        if !capture.is_opened()? {
+            return Err(opencv::Error::new(opencv::core::StsError, format!("camera {} is not available", self.index)));
+        }
+        if self.width > 0 {
+            capture.set(videoio::CAP_PROP_FRAME_WIDTH, self.width as f64)?;
+        }
+        if self.height > 0 {
+            capture.set(videoio::CAP_PROP_FRAME_HEIGHT, self.height as f64)?;
+        }
+        if self.fps > 0.0 {
+            capture.set(videoio::CAP_PROP_FPS, self.fps)?;
+        }
+        Ok(capture)
+    }
+}
+
+impl FrameSource for CameraSource {
+    fn name(&self) -> String {
+        format!("camera({})", self.index)
+    }
+
+    fn connect(&mut self) -> bool {
+        match self.open() {
+            Ok(capture) => {
+                info!("Camera {} opened", self.index);
+                self.capture = Some(capture);
+                true
+            }
+            Err(e) => {
+                error!("Failed to open camera {}: {}", self.index, e);
+                false
+            }
+        }
+    }
+
+    fn read(&mut self) -> anyhow::Result<SourceEvent> {
+        let capture = match self.capture {
+            Some(ref mut capture) => capture,
+            None => return Err(anyhow::anyhow!("camera is not opened")),
+        };
+        let mut image = Mat::default();
+        if !capture.read(&mut image)? {
+            return Err(anyhow::anyhow!("camera {} stopped delivering frames", self.index));
+        }
+        if image.empty() {
+            return Ok(SourceEvent::Idle);
+        }
+        Ok(SourceEvent::Image(image))
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(mut capture) = self.capture.take() {
+            let _ = capture.release();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender, TryRecvError, TrySendError};
+use serde::{Deserialize, Serialize};
+use super::image_msg::Frame;
+
+// 订阅者处理不过来时的丢帧策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SubscriberPolicy {
+    // 只保留最新一帧，适合预览
+    LatestOnly,
+    // 最多缓存 n 帧，满了丢弃最旧的，适合录制、标定等需要连续帧的场景
+    DropOldest(usize),
+}
+
+struct Subscriber<T> {
+    id: u64,
+    name: String,
+    tx: Sender<T>,
+    // 队列满时用来丢弃最旧的帧
+    drain_rx: Receiver<T>,
+}
+
+struct HubInner<T> {
+    next_id: u64,
+    subscribers: Vec<Subscriber<T>>,
+}
+
+// 一路图像流的帧分发，每个订阅者独立排队，帧通过 Arc 共享不做拷贝
+// 默认分发单路图像帧，双目配对后的帧等也复用同一套订阅逻辑
+pub struct FrameHub<T = Arc<Frame>> {
+    inner: Arc<Mutex<HubInner<T>>>,
+}
+
+impl<T> Clone for FrameHub<T> {
+    fn clone(&self) -> Self {
+        FrameHub {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for FrameHub<T> {
+    fn default() -> Self {
+        FrameHub {
+            inner: Arc::new(Mutex::new(HubInner {
+                next_id: 0,
+                subscribers: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl<T: Clone> FrameHub<T> {
+    pub fn new() -> Self {
+        FrameHub::default()
+    }
+
+    pub fn subscribe(&self, name: &str, policy: SubscriberPolicy) -> FrameSubscription<T> {
+        let capacity = match policy {
+            SubscriberPolicy::LatestOnly => 1,
+            SubscriberPolicy::DropOldest(capacity) => capacity.max(1),
+        };
+        let (tx, rx) = crossbeam::channel::bounded(capacity);
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.subscribers.push(Subscriber {
+            id,
+            name: name.to_string(),
+            tx,
+            drain_rx: rx.clone(),
+        });
+        FrameSubscription {
+            id,
+            name: name.to_string(),
+            hub: self.clone(),
+            rx,
+        }
+    }
+
+    pub fn publish(&self, frame: T) {
+        let inner = self.inner.lock().unwrap();
+        for subscriber in inner.subscribers.iter() {
+            if let Err(TrySendError::Full(frame)) = subscriber.tx.try_send(frame.clone()) {
+                let _ = subscriber.drain_rx.try_recv();
+                let _ = subscriber.tx.try_send(frame);
+            }
+        }
+    }
+}
+
+impl<T> FrameHub<T> {
+    pub fn subscriber_count(&self) -> usize {
+        self.inner.lock().unwrap().subscribers.len()
+    }
+
+    pub fn subscriber_names(&self) -> Vec<String> {
+        self.inner.lock().unwrap().subscribers.iter().map(|subscriber| subscriber.name.clone()).collect()
+    }
+
+    fn unsubscribe(&self, id: u64) {
+        self.inner.lock().unwrap().subscribers.retain(|subscriber| subscriber.id != id);
+    }
+}
+
+// 订阅句柄，drop 时自动退订
+pub struct FrameSubscription<T = Arc<Frame>> {
+    id: u64,
+    name: String,
+    hub: FrameHub<T>,
+    rx: Receiver<T>,
+}
+
+impl<T> FrameSubscription<T> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    // 供 select! 同时等待多路订阅
+    pub fn receiver(&self) -> &Receiver<T> {
+        &self.rx
+    }
+}
+
+impl<T> Drop for FrameSubscription<T> {
+    fn drop(&mut self) {
+        self.hub.unsubscribe(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use opencv::core::Mat;
+
+    fn frame() -> Arc<Frame> {
+        Arc::new(Frame::new(Mat::default(), Instant::now()))
+    }
+
+    #[test]
+    fn latest_only_keeps_newest_frame() {
+        let hub = FrameHub::new();
+        let subscription = hub.subscribe("preview", SubscriberPolicy::LatestOnly);
+        let frames: Vec<Arc<Frame>> = (0..3).map(|_| frame()).collect();
+        for frame in frames.iter() {
+            hub.publish(frame.clone());
+        }
+        assert!(Arc::ptr_eq(&subscription.try_recv().unwrap(), &frames[2]));
+        assert_eq!(subscription.try_recv().unwrap_err(), TryRecvError::Empty);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_last_frames_in_order() {
+        let hub = FrameHub::new();
+        let subscription = hub.subscribe("recorder", SubscriberPolicy::DropOldest(2));
+        let frames: Vec<Arc<Frame>> = (0..4).map(|_| frame()).collect();
+        for frame in frames.iter() {
+            hub.publish(frame.clone());
+        }
+        assert!(Arc::ptr_eq(&subscription.try_recv().unwrap(), &frames[2]));
+        assert!(Arc::ptr_eq(&subscription.try_recv().unwrap(), &frames[3]));
+        assert_eq!(subscription.try_recv().unwrap_err(), TryRecvError::Empty);
+    }
+
+    #[test]
+    fn drop_oldest_zero_keeps_one_frame() {
+        let hub = FrameHub::new();
+        let subscription = hub.subscribe("recorder", SubscriberPolicy::DropOldest(0));
+        let frames: Vec<Arc<Frame>> = (0..2).map(|_| frame()).collect();
+        for frame in frames.iter() {
+            hub.publish(frame.clone());
+        }
+        assert!(Arc::ptr_eq(&subscription.try_recv().unwrap(), &frames[1]));
+        assert_eq!(subscription.try_recv().unwrap_err(), TryRecvError::Empty);
+    }
+
+    #[test]
+    fn subscribers_share_frame_without_copy() {
+        let hub = FrameHub::new();
+        let preview = hub.subscribe("preview", SubscriberPolicy::LatestOnly);
+        let recorder = hub.subscribe("recorder", SubscriberPolicy::DropOldest(4));
+        let published = frame();
+        hub.publish(published.clone());
+        let preview_frame = preview.recv_timeout(Duration::from_millis(100)).unwrap();
+        let recorder_frame = recorder.recv_timeout(Duration::from_millis(100)).unwrap();
+        assert!(Arc::ptr_eq(&preview_frame, &published));
+        assert!(Arc::ptr_eq(&recorder_frame, &published));
+    }
+
+    #[test]
+    fn slow_subscriber_does_not_block_others() {
+        let hub = FrameHub::new();
+        let _slow = hub.subscribe("slow", SubscriberPolicy::LatestOnly);
+        let fast = hub.subscribe("fast", SubscriberPolicy::DropOldest(8));
+        for _ in 0..8 {
+            hub.publish(frame());
+        }
+        let mut received = 0;
+        while fast.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 8);
+    }
+
+    #[test]
+    fn dropping_subscription_unsubscribes() {
+        let hub = FrameHub::new();
+        assert_eq!(hub.subscriber_count(), 0);
+        let preview = hub.subscribe("preview", SubscriberPolicy::LatestOnly);
+        let recorder = hub.subscribe("recorder", SubscriberPolicy::DropOldest(4));
+        assert_eq!(hub.subscriber_count(), 2);
+        assert_ne!(preview.id(), recorder.id());
+        drop(preview);
+        assert_eq!(hub.subscriber_count(), 1);
+        assert_eq!(hub.subscriber_names(), vec!["recorder".to_string()]);
+        // 退订后继续发布不影响剩下的订阅者
+        hub.publish(frame());
+        assert!(recorder.try_recv().is_ok());
+        drop(recorder);
+        assert_eq!(hub.subscriber_count(), 0);
+    }
+}
@@ -0,0 +1,84 @@
+use opencv::core::Mat;
+use serde::{Deserialize, Serialize};
+use super::{camera_source::CameraSource, image_msg::DeviceStatus, latency::DeviceFrameInfo, replay_source::ReplaySource, ws_source::WebSocketSource};
+
+// 图像来源配置，每个设备单独选择
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrameSourceConfig {
+    // 设备通过 WiFi websocket 推送 JPEG
+    #[default]
+    WebSocket,
+    // 本地 USB/UVC 摄像头
+    Camera {
+        index: i32,
+        #[serde(default)]
+        width: i32,
+        #[serde(default)]
+        height: i32,
+        #[serde(default)]
+        fps: f64,
+    },
+    // 回放目录中按文件名排序的 JPEG 帧
+    Replay {
+        path: String,
+        #[serde(default = "default_replay_fps")]
+        fps: f64,
+        #[serde(default = "default_replay_looped")]
+        looped: bool,
+    },
+}
+
+fn default_replay_fps() -> f64 {
+    30.0
+}
+
+fn default_replay_looped() -> bool {
+    true
+}
+
+// 一次读取的结果
+#[derive(Debug)]
+pub enum SourceEvent {
+    // 设备原始 JPEG 数据，以及设备上报的帧序号和采集时间
+    Jpeg(Vec<u8>, Option<DeviceFrameInfo>),
+    // 已解码的图像
+    Image(Mat),
+    // 设备上报的状态
+    Status(DeviceStatus),
+    // 对端主动关闭
+    Closed,
+    // 本次没有可用数据
+    Idle,
+}
+
+pub trait FrameSource: Send {
+    fn name(&self) -> String;
+
+    fn connect(&mut self) -> bool;
+
+    // 读取失败时返回 Err，调用方负责断开并重连
+    fn read(&mut self) -> anyhow::Result<SourceEvent>;
+
+    fn disconnect(&mut self);
+
+    // 串口上报的新地址，只有网络来源需要处理
+    fn set_address(&mut self, _address: &str) {}
+
+    // 向设备发送控制消息，只有 websocket 来源支持
+    fn send_text(&mut self, _text: &str) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("{} does not support control messages", self.name()))
+    }
+}
+
+pub fn create_frame_source(config: &FrameSourceConfig, device_type: i32, ip: String) -> Box<dyn FrameSource> {
+    match config {
+        FrameSourceConfig::WebSocket => Box::new(WebSocketSource::new(device_type, ip)),
+        FrameSourceConfig::Camera { index, width, height, fps } => {
+            Box::new(CameraSource::new(*index, *width, *height, *fps))
+        }
+        FrameSourceConfig::Replay { path, fps, looped } => {
+            Box::new(ReplaySource::new(path.clone(), *fps, *looped))
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
-use opencv::core::Mat;
-use std::time::Instant;
+use opencv::{core::{Mat, MatTraitConst, Size, Vector}, imgcodecs, imgproc};
+use once_cell::sync::OnceCell;
+use std::{sync::{Arc, Mutex}, time::Instant};
+use super::{latency::FrameTiming, preprocess::PreprocessStage};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PortState {
     Connected,
     Disconnected,
@@ -14,16 +16,61 @@ pub struct DeviceStatus {
     pub brightness: i32,
 }
 
+// 通过 websocket 文本帧发送给设备的控制消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ImageRequest {
-    GetImageBase64,
-    GetImageOpenCV,
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum WifiControlMessage {
+    SetBrightness { value: i32 },
+    SetEnergyMode { mode: i32 },
+    Restart,
+    GetStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StreamSettingRequest {
     GetDeviceStatus,
     SetRotateAngle(f64),
+    // flip x, flip y
+    SetFlip(bool, bool),
+    SetPreprocess(Vec<PreprocessStage>),
+    // stage index, enabled
+    SetPreprocessStageEnabled(usize, bool),
+    SendControl(WifiControlMessage),
+    // 手动指定设备地址，立即断开并重连
+    SetAddress(String),
+}
+
+// 合并串口与 WiFi 两路信息后的设备状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamStatus {
+    pub serial: bool,
+    pub wifi: bool,
+    pub ip: String,
+    pub battery: f32,
+    pub brightness: i32,
+    pub device_type: i32,
+}
+
+// 图像流写入、预览线程读取的状态，版本号在内容变化时递增
+#[derive(Debug, Default)]
+pub struct StatusCell {
+    inner: Mutex<(u64, StreamStatus)>,
+}
+
+impl StatusCell {
+    pub fn update(&self, status: StreamStatus) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.1 == status {
+            return false;
+        }
+        inner.0 += 1;
+        inner.1 = status;
+        true
+    }
+
+    pub fn get(&self) -> (u64, StreamStatus) {
+        self.inner.lock().unwrap().clone()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,10 +78,29 @@ pub enum StreamSettingResponse {
     DeviceStatus(DeviceStatus),
 }
 
-#[derive(Debug, Clone)]
-pub enum ImageResponse {
-    Base64ImageData(Vec<u8>),
-    OpenCVImageData(Mat),
+// 预览默认 JPEG 质量
+pub const PREVIEW_JPEG_QUALITY: i32 = 90;
+// 预览默认帧率上限
+pub const PREVIEW_FPS: f64 = 30.0;
+
+// 每个预览窗口独立的编码设置，只作用于预览分支，不影响跟踪用的原始帧
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PreviewSettings {
+    // 0 表示不缩放
+    pub max_width: i32,
+    pub jpeg_quality: i32,
+    pub fps: f64,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        PreviewSettings {
+            max_width: 0,
+            jpeg_quality: PREVIEW_JPEG_QUALITY,
+            fps: PREVIEW_FPS,
+        }
+    }
 }
 
 // Frame with metadata
@@ -42,4 +108,77 @@ pub enum ImageResponse {
 pub struct Frame {
     pub image: Mat,
     pub timestamp: Instant,
+    pub timing: FrameTiming,
+    // 预览用的 JPEG 编码，每帧最多编码一次；无变换时直接存放设备原始 JPEG
+    encoded: OnceCell<Arc<Vec<u8>>>,
+}
+
+impl Frame {
+    pub fn new(image: Mat, timestamp: Instant) -> Self {
+        Frame {
+            image,
+            timestamp,
+            timing: FrameTiming::received(timestamp),
+            encoded: OnceCell::new(),
+        }
+    }
+
+    // 图像未经任何变换，预览可直接转发设备 JPEG，无需重新编码
+    pub fn with_jpeg(image: Mat, timestamp: Instant, jpeg: Vec<u8>) -> Self {
+        Frame {
+            image,
+            timestamp,
+            timing: FrameTiming::received(timestamp),
+            encoded: OnceCell::with_value(Arc::new(jpeg)),
+        }
+    }
+
+    pub fn with_timing(mut self, timing: FrameTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    // 有设备时间戳时返回采集时间，否则为本机收到的时间
+    pub fn capture_time(&self) -> Instant {
+        self.timing.captured_at.unwrap_or(self.timestamp)
+    }
+
+    pub fn encoded_jpeg(&self) -> opencv::Result<Arc<Vec<u8>>> {
+        self.encoded.get_or_try_init(|| {
+            encode_jpeg(&self.image, PREVIEW_JPEG_QUALITY).map(Arc::new)
+        }).cloned()
+    }
+
+    // 预览实际发送的尺寸，超过 max_width 时按比例缩小
+    pub fn preview_size(&self, settings: &PreviewSettings) -> (i32, i32) {
+        let (width, height) = (self.image.cols(), self.image.rows());
+        if settings.max_width <= 0 || width <= settings.max_width {
+            return (width, height);
+        }
+        let scale = settings.max_width as f64 / width as f64;
+        (settings.max_width, ((height as f64 * scale).round() as i32).max(1))
+    }
+
+    // 按预览设置编码；不需要缩放且质量为默认值时复用每帧缓存
+    pub fn encode_preview(&self, settings: &PreviewSettings) -> opencv::Result<Arc<Vec<u8>>> {
+        let (width, height) = self.preview_size(settings);
+        let needs_resize = width != self.image.cols();
+        if !needs_resize && settings.jpeg_quality == PREVIEW_JPEG_QUALITY {
+            return self.encoded_jpeg();
+        }
+        let quality = settings.jpeg_quality.clamp(1, 100);
+        if !needs_resize {
+            return encode_jpeg(&self.image, quality).map(Arc::new);
+        }
+        let mut resized = Mat::default();
+        imgproc::resize(&self.image, &mut resized, Size::new(width, height), 0.0, 0.0, imgproc::INTER_AREA)?;
+        encode_jpeg(&resized, quality).map(Arc::new)
+    }
+}
+
+fn encode_jpeg(image: &Mat, quality: i32) -> opencv::Result<Vec<u8>> {
+    let mut encoded_data = Vector::<u8>::new();
+    let params = Vector::<i32>::from_slice(&[imgcodecs::IMWRITE_JPEG_QUALITY, quality]);
+    imgcodecs::imencode(".jpg", image, &mut encoded_data, &params)?;
+    Ok(encoded_data.into())
 }
\ No newline at end of file
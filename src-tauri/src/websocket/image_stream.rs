@@ -1,65 +1,84 @@
-use std::{collections::VecDeque, sync::mpsc::TryRecvError, time::Instant};
+use std::{sync::{atomic::{AtomicBool, AtomicI32, Ordering}, mpsc::TryRecvError, Arc, Mutex}, time::{Duration, Instant}};
 use crossbeam::channel::{Sender, Receiver};
-use opencv::{core::{Mat, MatTraitConst, Vector}, imgcodecs};
+use opencv::{core::{Mat, MatTrait, MatTraitConst}, imgcodecs};
 use tauri::{App, AppHandle, Emitter, Runtime};
 use crate::{serial::serial_msg::SerialMessage, utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE}};
-use super::image_msg::{DeviceStatus, Frame, ImageRequest, ImageResponse, PortState, StreamSettingRequest, StreamSettingResponse};
-use url::Url;
-use tungstenite::{connect, Message, WebSocket};
-use base64::{Engine as _, engine::general_purpose};
+use super::{frame_hub::FrameHub, latency::{ClockSync, DeviceFrameInfo, FrameTiming, LatencyStage, LatencyStats}, reconnect::{ConnectionStats, ReconnectBackoff, StaleWatchdog}, frame_source::{FrameSource, SourceEvent}, preprocess::PreprocessPipeline};
+use super::image_msg::{DeviceStatus, Frame, PortState, StatusCell, StreamSettingRequest, StreamSettingResponse, StreamStatus, WifiControlMessage};
 use ftlog::*;
 
+// 连接保持但超过这个时间没有新帧，视为连接失效
+const STALE_STREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct ImageStream<R: Runtime> {
-    request_rx: Receiver<ImageRequest>,
-    request_tx: Sender<ImageRequest>,
-    img_response_tx: bus::Bus<ImageResponse>,
+    // 每个新帧只发布一次，由 hub 分发给各个订阅者
+    frame_hub: FrameHub,
     setting_response_tx: bus::Bus<StreamSettingResponse>,
     settings_rx: Receiver<StreamSettingRequest>,
     settings_tx: Sender<StreamSettingRequest>,
     serial_msg_rx: bus::BusReader<SerialMessage>,
+    source: Box<dyn FrameSource>,
 
     device_type: i32,
     port_state: PortState,
+    // 供控制命令判断 WiFi 通道是否可用
+    connected: Arc<AtomicBool>,
     ip: String,
+    // 用户通过 set_device_ip 手动指定地址后，不再使用串口状态包里的 IP，直到程序重启
+    manual_ip: bool,
     run: bool,
     device_status: DeviceStatus,
-    image_buffer: VecDeque<Frame>,
+    // 串口线程维护的设备类型，端口断开时清空，用来判断本设备是否插着数据线
+    serial_device_type: Arc<AtomicI32>,
+    status: Arc<StatusCell>,
+    watchdog: StaleWatchdog,
+    backoff: ReconnectBackoff,
+    stats: Arc<Mutex<ConnectionStats>>,
+    clock_sync: ClockSync,
+    latency: Arc<Mutex<LatencyStats>>,
     rotate_angle: f64,
+    flip_x: bool,
+    flip_y: bool,
+    preprocess: PreprocessPipeline,
 
     app_handle: AppHandle<R>,
 }
 
 impl<R: Runtime> ImageStream<R> {
-    pub fn new(serial_msg_rx: bus::BusReader<SerialMessage>, ip: String, device_type: i32, app: AppHandle<R>) -> Self {
-        let (request_tx, request_rx) = crossbeam::channel::unbounded();
-        let img_response_tx = bus::Bus::<ImageResponse>::new(1);
+    pub fn new(serial_msg_rx: bus::BusReader<SerialMessage>, serial_device_type: Arc<AtomicI32>, source: Box<dyn FrameSource>, ip: String, device_type: i32, app: AppHandle<R>) -> Self {
         let (settings_tx, settings_rx) = crossbeam::channel::unbounded();
         let setting_response_tx = bus::Bus::<StreamSettingResponse>::new(1);
         ImageStream {
-            request_rx,
-            request_tx,
-            img_response_tx,
+            frame_hub: FrameHub::new(),
             setting_response_tx,
             settings_rx,
             settings_tx,
             serial_msg_rx,
+            source,
             device_type,
             port_state: PortState::Disconnected,
+            connected: Arc::new(AtomicBool::new(false)),
             ip,
+            manual_ip: false,
             run: false,
             device_status: DeviceStatus { battery: 0.0, brightness: 0 },
-            image_buffer: VecDeque::new(),
+            serial_device_type,
+            status: Arc::new(StatusCell::default()),
+            watchdog: StaleWatchdog::new(STALE_STREAM_TIMEOUT),
+            backoff: ReconnectBackoff::default(),
+            stats: Arc::new(Mutex::new(ConnectionStats::default())),
+            clock_sync: ClockSync::default(),
+            latency: Arc::new(Mutex::new(LatencyStats::default())),
             rotate_angle: 0.0,
+            flip_x: false,
+            flip_y: false,
+            preprocess: PreprocessPipeline::default(),
             app_handle: app
         }
     }
 
-    pub fn get_request_tx(&self) -> Sender<ImageRequest> {
-        self.request_tx.clone()
-    }
-
-    pub fn get_response_rx(&mut self) -> bus::BusReader<ImageResponse> {
-        self.img_response_tx.add_rx()
+    pub fn get_frame_hub(&self) -> FrameHub {
+        self.frame_hub.clone()
     }
 
     pub fn get_setting_response_rx(&mut self) -> bus::BusReader<StreamSettingResponse> {
@@ -70,38 +89,27 @@ impl<R: Runtime> ImageStream<R> {
         self.settings_tx.clone()
     }
 
+    pub fn get_connected_flag(&self) -> Arc<AtomicBool> {
+        self.connected.clone()
+    }
+
+    pub fn get_status_cell(&self) -> Arc<StatusCell> {
+        self.status.clone()
+    }
+
+    pub fn get_stats(&self) -> Arc<Mutex<ConnectionStats>> {
+        self.stats.clone()
+    }
+
+    pub fn get_latency(&self) -> Arc<Mutex<LatencyStats>> {
+        self.latency.clone()
+    }
+
     pub fn start(&mut self) {
-        let mut port: Option<WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>> = None;
         self.run = true;
+        self.emit_port_state();
         loop {
-            if let PortState::Connected = self.port_state {
-                match self.device_type {
-                    DEVICE_TYPE_FACE => {
-                        let _ = self.app_handle.emit("face_image_stream_status", "面捕WIFI已连接");
-                    }
-                    DEVICE_TYPE_LEFT_EYE => {
-                        let _ = self.app_handle.emit("left_eye_image_stream_status", "左眼WIFI已连接");
-                    }
-                    DEVICE_TYPE_RIGHT_EYE => {
-                        let _ = self.app_handle.emit("right_eye_image_stream_status", "右眼WIFI已连接");
-                    }
-                    _ => ()
-                }
-            }
-            if let PortState::Disconnected = self.port_state {
-                match self.device_type {
-                    DEVICE_TYPE_FACE => {
-                        let _ = self.app_handle.emit("face_image_stream_status", "面捕WIFI未连接");
-                    }
-                    DEVICE_TYPE_LEFT_EYE => {
-                        let _ = self.app_handle.emit("left_eye_image_stream_status", "左眼WIFI未连接");
-                    }
-                    DEVICE_TYPE_RIGHT_EYE => {
-                        let _ = self.app_handle.emit("right_eye_image_stream_status", "右眼WIFI未连接");
-                    }
-                    _ => ()
-                }
-            }
+            self.update_status();
             match self.settings_rx.try_recv() {
                 Ok(request) => {
                     match request {
@@ -112,6 +120,35 @@ impl<R: Runtime> ImageStream<R> {
                             self.rotate_angle = angle;
                             info!("Set rotate angle to: {}", angle);
                         }
+                        StreamSettingRequest::SetFlip(flip_x, flip_y) => {
+                            self.flip_x = flip_x;
+                            self.flip_y = flip_y;
+                            info!("Set flip to: x = {}, y = {}", flip_x, flip_y);
+                        }
+                        StreamSettingRequest::SetAddress(ip) => {
+                            info!("Stream {} address set to: {}", self.device_type, ip);
+                            self.ip = ip;
+                            self.manual_ip = true;
+                            self.source.set_address(&self.ip);
+                            self.source.disconnect();
+                            self.set_port_state(PortState::Disconnected);
+                            // 手动修改地址后立即重连
+                            self.backoff.reset();
+                        }
+                        StreamSettingRequest::SendControl(message) => {
+                            self.send_control(message);
+                        }
+                        StreamSettingRequest::SetPreprocess(stages) => {
+                            info!("Set preprocess pipeline with {} stages", stages.len());
+                            self.preprocess.set_stages(stages);
+                        }
+                        StreamSettingRequest::SetPreprocessStageEnabled(index, enabled) => {
+                            if self.preprocess.set_stage_enabled(index, enabled) {
+                                info!("Set preprocess stage {} enabled: {}", index, enabled);
+                            } else {
+                                warn!("Preprocess stage {} does not exist", index);
+                            }
+                        }
                     }
                 }
                 Err(crossbeam::channel::TryRecvError::Disconnected) => {
@@ -120,17 +157,6 @@ impl<R: Runtime> ImageStream<R> {
                 }
                 _ => ()
             }
-            // Handle settings requests
-            match self.request_rx.try_recv() {
-                Ok(request) => {
-                    self.handle_request(request);
-                }
-                Err(crossbeam::channel::TryRecvError::Disconnected) => {
-                    // Handle error in receiving requests
-                    error!("disconnected request in serial");
-                }
-                _ => ()
-            }
             match self.serial_msg_rx.try_recv() {
                 Ok(msg) => {
                     self.handle_serial_message(msg);
@@ -143,8 +169,17 @@ impl<R: Runtime> ImageStream<R> {
             }
             if let PortState::Disconnected = self.port_state {
                 if self.run {
-                    if self.connect(&mut port) {
-                        info!("Stream {} Connected to: {}", self.device_type, self.ip);
+                    // 退避时间未到时短暂休眠，保证设置请求仍能及时处理
+                    if !self.backoff.ready() {
+                        std::thread::sleep(Duration::from_millis(100));
+                        continue;
+                    }
+                    if self.source.connect() {
+                        info!("Stream {} Connected to: {}", self.device_type, self.source.name());
+                        self.stats.lock().unwrap().record(self.source.name(), true, "connected");
+                        self.watchdog.connected(Instant::now());
+                        // 重连后设备可能已重启，时钟偏移需要重新估计
+                        self.clock_sync.reset();
                         match self.device_type {
                             DEVICE_TYPE_FACE => {
                                 let _ = self.app_handle.emit("face_ip", self.ip.as_str());
@@ -157,9 +192,10 @@ impl<R: Runtime> ImageStream<R> {
                             }
                             _ => ()
                         }
-                        self.port_state = PortState::Connected;
+                        self.set_port_state(PortState::Connected);
                     } else {
-                        std::thread::sleep(std::time::Duration::from_secs(1));
+                        self.stats.lock().unwrap().record(self.source.name(), false, "connect failed");
+                        self.schedule_reconnect();
                         continue;
                     }
                 } else {
@@ -171,210 +207,247 @@ impl<R: Runtime> ImageStream<R> {
                 std::thread::sleep(std::time::Duration::from_secs(1));
                 continue;
             }
-            if let Some(ref mut port) = port {
-                match port.read() {
-                    Ok(msg) => {
-                        self.handle_websocket_message(msg);
-                    }
-                    Err(e) => {
-                        error!("Stream {} Error reading from port: {}", self.device_type, e);
-                        self.port_state = PortState::Disconnected;
-                    }
+            match self.source.read() {
+                Ok(event) => {
+                    self.handle_source_event(event);
                 }
+                Err(e) => {
+                    error!("Stream {} Error reading from {}: {}", self.device_type, self.source.name(), e);
+                    self.drop_connection(&format!("read error: {}", e));
+                    continue;
+                }
+            }
+            // 看门狗：socket 没断但设备不再推流
+            if self.port_state == PortState::Connected && self.watchdog.is_stale(Instant::now()) {
+                warn!("Stream {} no frame for {:?}, reconnecting", self.device_type, STALE_STREAM_TIMEOUT);
+                self.stats.lock().unwrap().stale_disconnects += 1;
+                self.drop_connection("stale stream");
             }
         }
     }
 
-    fn handle_websocket_message(&mut self, msg: Message) {
-        match msg {
-            Message::Binary(data) => {
-                // Process image data
-                if data.len() < 10 {
-                    warn!("Received binary data too small to be an image");
-                    return ;
+    fn schedule_reconnect(&mut self) {
+        let delay = self.backoff.failure();
+        self.stats.lock().unwrap().current_backoff_ms = delay.as_millis() as u64;
+        debug!("Stream {} retry in {:?}", self.device_type, delay);
+    }
+
+    fn drop_connection(&mut self, reason: &str) {
+        info!("Stream {} dropping connection: {}", self.device_type, reason);
+        self.source.disconnect();
+        self.set_port_state(PortState::Disconnected);
+        // 刚连上就断开的情况也要退避，避免频繁重连
+        if !self.watchdog.received_frame() {
+            self.schedule_reconnect();
+        }
+    }
+
+    fn mark_frame_received(&mut self) {
+        if self.watchdog.frame(Instant::now()) {
+            self.backoff.reset();
+        }
+        self.stats.lock().unwrap().frames_received += 1;
+    }
+
+    fn set_port_state(&mut self, state: PortState) {
+        self.connected.store(state == PortState::Connected, Ordering::Relaxed);
+        let changed = self.port_state != state;
+        self.port_state = state;
+        if changed {
+            self.emit_port_state();
+        }
+    }
+
+    fn emit_port_state(&self) {
+        let connected = self.port_state == PortState::Connected;
+        let (event, text) = match (self.device_type, connected) {
+            (DEVICE_TYPE_FACE, true) => ("face_image_stream_status", "面捕WIFI已连接"),
+            (DEVICE_TYPE_FACE, false) => ("face_image_stream_status", "面捕WIFI未连接"),
+            (DEVICE_TYPE_LEFT_EYE, true) => ("left_eye_image_stream_status", "左眼WIFI已连接"),
+            (DEVICE_TYPE_LEFT_EYE, false) => ("left_eye_image_stream_status", "左眼WIFI未连接"),
+            (DEVICE_TYPE_RIGHT_EYE, true) => ("right_eye_image_stream_status", "右眼WIFI已连接"),
+            (DEVICE_TYPE_RIGHT_EYE, false) => ("right_eye_image_stream_status", "右眼WIFI未连接"),
+            _ => return,
+        };
+        let _ = self.app_handle.emit(event, text);
+    }
+
+    // 状态有变化时才会递增版本，预览线程据此推送 StreamEvent::Status
+    // 同时广播 device_status 事件，没有打开预览的窗口也能收到
+    fn update_status(&self) {
+        let status = StreamStatus {
+            serial: self.serial_device_type.load(Ordering::Relaxed) == self.device_type,
+            wifi: self.port_state == PortState::Connected,
+            ip: self.ip.clone(),
+            battery: self.device_status.battery,
+            brightness: self.device_status.brightness,
+            device_type: self.device_type,
+        };
+        if self.status.update(status.clone()) {
+            let _ = self.app_handle.emit("device_status", status);
+        }
+    }
+
+    fn send_control(&mut self, message: WifiControlMessage) {
+        if self.port_state != PortState::Connected {
+            warn!("Stream {} is not connected, drop control message: {:?}", self.device_type, message);
+            return;
+        }
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to serialize control message: {}", e);
+                return;
+            }
+        };
+        match self.source.send_text(&text) {
+            Ok(_) => {
+                info!("Stream {} sent control message: {}", self.device_type, text);
+            }
+            Err(e) => {
+                error!("Stream {} failed to send control message: {}", self.device_type, e);
+                self.drop_connection("control send failed");
+            }
+        }
+    }
+
+    fn handle_source_event(&mut self, event: SourceEvent) {
+        match event {
+            SourceEvent::Jpeg(data, info) => {
+                let timing = self.frame_timing(info);
+                self.mark_frame_received();
+                // 没有订阅者时不解码，保持连接即可
+                if self.frame_hub.subscriber_count() == 0 {
+                    return;
                 }
                 // Decode image with OpenCV
                 match imgcodecs::imdecode(&Mat::from_slice(&data).unwrap(), imgcodecs::IMREAD_COLOR) {
                     Ok(image) if !image.empty() => {
-                        // Create a new frame and add it to the buffer
-                        let mut frame = Frame {
-                            image,
-                            timestamp: Instant::now(),
-                        };
-                        // Keep only the most recent frame
-                        self.image_buffer.clear();
-                        let center = opencv::core::Point2f::new((frame.image.cols() / 2) as f32, (frame.image.rows() / 2) as f32);
-                        let rotation_matrix = opencv::imgproc::get_rotation_matrix_2d(center, self.rotate_angle, 1.0).unwrap();
-                        let mut rotated_image = opencv::core::Mat::default();
-                        opencv::imgproc::warp_affine(
-                            &frame.image,
-                            &mut rotated_image,
-                            &rotation_matrix,
-                            frame.image.size().unwrap(),
-                            opencv::imgproc::INTER_LINEAR,
-                            opencv::core::BORDER_CONSTANT,
-                            opencv::core::Scalar::default(),
-                        ).unwrap();
-                        frame.image = rotated_image;
-                        self.image_buffer.push_back(frame);
+                        self.latency.lock().unwrap().record(LatencyStage::Decode, timing.received_at.elapsed());
+                        self.push_image(image, Some(data), timing);
                     }
                     Ok(_) => {
-                        println!("Decoded image is empty");
+                        warn!("Decoded image is empty");
                     }
                     Err(e) => {
-                        println!("Failed to decode image: {}", e);
+                        warn!("Failed to decode image: {}", e);
                     }
-                }            
+                }
             }
-            Message::Text(text) => {
-                match serde_json::from_str::<DeviceStatus>(&text) {
-                    Ok(status) => {
-                        self.device_status = status.clone();
-                    }
-                    Err(e) => {
-                        println!("Failed to parse status message: {}, message: {}", e, text);
-                    }
+            SourceEvent::Image(image) => {
+                let timing = self.frame_timing(None);
+                self.mark_frame_received();
+                if self.frame_hub.subscriber_count() == 0 {
+                    return;
                 }
+                self.push_image(image, None, timing);
+            }
+            SourceEvent::Status(status) => {
+                self.device_status = status;
             }
-            Message::Close(_) => {
+            SourceEvent::Closed => {
                 // Handle close message
                 info!("Stream {} Connection closed", self.device_type);
-                self.port_state = PortState::Disconnected;
+                self.drop_connection("closed by device");
             }
-            _ => ()
+            SourceEvent::Idle => (),
         }
     }
 
-    fn handle_request(&mut self, request: ImageRequest) {
-        match request {
-            ImageRequest::GetImageBase64 => {
-                self.get_image_base64();
-            }
-            ImageRequest::GetImageOpenCV => {
-                self.get_image_opencv();
-            }
+    // 记录收到帧的时间，有设备时间戳时更新时钟偏移并统计传输延迟
+    fn frame_timing(&mut self, info: Option<DeviceFrameInfo>) -> FrameTiming {
+        let mut timing = FrameTiming::received(Instant::now());
+        let info = match info {
+            Some(info) => info,
+            None => return timing,
+        };
+        let lost = self.clock_sync.update(&info, timing.received_at);
+        timing.device = Some(info);
+        timing.captured_at = self.clock_sync.to_host(info.capture_us);
+        let mut latency = self.latency.lock().unwrap();
+        latency.lost_frames += lost;
+        latency.last_frame_id = Some(info.frame_id);
+        latency.clock_offset_us = self.clock_sync.offset_us();
+        if let Some(captured_at) = timing.captured_at {
+            latency.record(LatencyStage::Transport, timing.received_at.saturating_duration_since(captured_at));
         }
+        timing
     }
 
-    fn connect(&mut self, port: &mut Option<WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>>) -> bool {
-        let mut ip_list = Vec::new();
-        match self.device_type {
-            DEVICE_TYPE_FACE => {
-                ip_list.push("ws://paper1.local:80/ws");
-            },
-            DEVICE_TYPE_LEFT_EYE => {
-                ip_list.push("ws://paper2.local:80/ws");
-            }
-            DEVICE_TYPE_RIGHT_EYE => {
-                ip_list.push("ws://paper3.local:80/ws");
+    fn push_image(&mut self, image: Mat, jpeg: Option<Vec<u8>>, timing: FrameTiming) {
+        let process_start = Instant::now();
+        // 没有任何变换时直接保留设备 JPEG，预览不必重新编码
+        if self.is_identity_transform() {
+            let frame = match jpeg {
+                Some(jpeg) => Frame::with_jpeg(image, Instant::now(), jpeg),
+                None => Frame::new(image, Instant::now()),
+            };
+            self.publish_frame(frame.with_timing(timing), process_start);
+            return;
+        }
+        match self.transform_image(&image).and_then(|image| self.preprocess.apply(&image)) {
+            Ok(image) => {
+                self.publish_frame(Frame::new(image, Instant::now()).with_timing(timing), process_start);
             }
-            _ => {
-                error!("Unknown device type: {}", self.device_type);
+            Err(e) => {
+                error!("Stream {} Failed to transform image: {}", self.device_type, e);
             }
         }
-        // make ip live longer
-        let ip_formated = self.get_connect_url_from_self_ip();
-        if !self.ip.is_empty() {
-            ip_list.push(ip_formated.as_str());
-        }
-
-        for url_to_try in ip_list {
-            info!("Stream {} Trying to connect to: {}", self.device_type, url_to_try);
-            match Url::parse(url_to_try) {
-                Ok(url) => {
-                    match connect(url) {
-                        Ok((mut ws, _)) => {
-                            info!("Stream {} Connected to: {}", self.device_type, url_to_try);
+    }
 
-                            if let tungstenite::stream::MaybeTlsStream::Plain(stream) = ws.get_mut() {
-                                if let Err(e) = stream.set_read_timeout(Some(std::time::Duration::from_secs(2))) {
-                                    warn!("Stream {} Failed to set read timeout: {}", self.device_type, e);
-                                }
-                            }
-                            *port = Some(ws);
-                            return true;
-                        }
-                        Err(e) => {
-                            error!("Stream {} Failed to connect to {}: {}", self.device_type, url_to_try, e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Stream {} Invalid URL {}: {}", self.device_type, url_to_try, e);
-                }
-            }
-        }
+    fn publish_frame(&mut self, frame: Frame, process_start: Instant) {
+        self.latency.lock().unwrap().record(LatencyStage::Process, process_start.elapsed());
+        self.frame_hub.publish(Arc::new(frame));
+    }
 
-        port.is_some()
+    fn is_identity_transform(&self) -> bool {
+        self.rotate_angle % 360.0 == 0.0 && !self.flip_x && !self.flip_y && self.preprocess.is_empty()
     }
 
-    fn get_connect_url_from_self_ip(&self) -> String {
-        if !self.ip.starts_with("ws://") && !self.ip.starts_with("wss://") {
-            if self.ip.starts_with("http://") {
-                format!("ws://{}/ws", self.ip.strip_prefix("http://").unwrap_or(&self.ip))
-            } else if self.ip.starts_with("https://") {
-                format!("wss://{}/ws", self.ip.strip_prefix("https://").unwrap_or(&self.ip))
-            } else {
-                // Assume host:port format
-                let mut ws_url = format!("ws://{}", self.ip);
-                if !ws_url.contains(':') {
-                    ws_url.push_str(":80");
-                }
-                if !ws_url.contains("/ws") {
-                    ws_url.push_str("/ws");
-                }
-                ws_url
-            }    
-        } else {
-            self.ip.clone()
+    // 旋转和翻转合并为一次仿射变换，避免对同一帧做两次 warp
+    fn transform_image(&self, image: &Mat) -> opencv::Result<Mat> {
+        if self.rotate_angle % 360.0 == 0.0 && !self.flip_x && !self.flip_y {
+            return Ok(image.clone());
         }
+        let size = image.size()?;
+        let center = opencv::core::Point2f::new((size.width / 2) as f32, (size.height / 2) as f32);
+        let mut matrix = opencv::imgproc::get_rotation_matrix_2d(center, self.rotate_angle, 1.0)?;
+        // 翻转即 x' = (w - 1) - x / y' = (h - 1) - y，左乘到旋转矩阵上
+        if self.flip_x {
+            for col in 0..3 {
+                *matrix.at_2d_mut::<f64>(0, col)? *= -1.0;
+            }
+            *matrix.at_2d_mut::<f64>(0, 2)? += (size.width - 1) as f64;
+        }
+        if self.flip_y {
+            for col in 0..3 {
+                *matrix.at_2d_mut::<f64>(1, col)? *= -1.0;
+            }
+            *matrix.at_2d_mut::<f64>(1, 2)? += (size.height - 1) as f64;
+        }
+        let mut transformed = Mat::default();
+        opencv::imgproc::warp_affine(
+            image,
+            &mut transformed,
+            &matrix,
+            size,
+            opencv::imgproc::INTER_LINEAR,
+            opencv::core::BORDER_CONSTANT,
+            opencv::core::Scalar::default(),
+        )?;
+        Ok(transformed)
     }
 
     fn handle_serial_message(&mut self, msg: SerialMessage) {
         if let SerialMessage::DeviceStatus(status) = msg {
             if self.device_type == status.device_type {
-                self.ip = status.ip.clone();
-            }
-        }
-    }
-
-    fn get_image_base64(&mut self) {
-        if let Some(frame) = self.image_buffer.front() {
-            // Convert image to base64
-            let mut encoded_data = Vector::<u8>::new();
-            
-            // 设置 JPEG 编码参数
-            let mut params = Vector::<i32>::new();
-            params.push(imgcodecs::IMWRITE_JPEG_QUALITY);
-            params.push(90); // JPEG 质量 (0-100)
-            
-            // 将 Mat 编码为 JPEG 格式
-            match imgcodecs::imencode(".jpg", &frame.image, &mut encoded_data, &params) {
-                Ok(_) => {
-                    // 将编码后的数据转换为 Vec<u8>
-                    let vec_data: Vec<u8> = encoded_data.into();
-                    
-                    // 使用 base64 编码
-                    let base64_string = general_purpose::STANDARD.encode(&vec_data);
-                    // 广播 base64 响应
-                    self.img_response_tx.broadcast(ImageResponse::Base64ImageData(base64_string.into_bytes()));
-                }
-                Err(e) => {
-                    error!("Failed to encode image to JPEG: {}", e);
+                if !self.manual_ip {
+                    self.ip = status.ip.clone();
+                    self.source.set_address(&status.ip);
                 }
+                self.device_status.battery = status.power;
+                self.device_status.brightness = status.brightness;
             }
-        } else {
-            debug!("No image available in buffer");
-        }
-    }
-    
-    fn get_image_opencv(&mut self) {
-        // Implement the logic to get image in OpenCV format
-        // This is a placeholder implementation
-        if let Some(frame) = self.image_buffer.front() {
-            self.img_response_tx.broadcast(ImageResponse::OpenCVImageData(frame.image.clone()));
-        } else {
-            debug!("No image available in buffer");
         }
     }
 
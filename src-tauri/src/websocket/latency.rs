@@ -0,0 +1,226 @@
+use std::{collections::{BTreeMap, VecDeque}, time::{Duration, Instant}};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+// 设备帧头：magic + u32 帧序号 + u64 采集时间（微秒），均为小端，后面紧跟 JPEG
+const FRAME_HEADER_MAGIC: &[u8; 4] = b"PTFH";
+const FRAME_HEADER_LEN: usize = 16;
+// 时钟偏移取最近这些样本的最小值，约等于网络延迟最小的那一帧
+const CLOCK_SAMPLE_LEN: usize = 120;
+// 平均延迟的平滑系数
+const LATENCY_EMA_ALPHA: f64 = 0.1;
+
+// 本机时钟零点，所有本机微秒时间都相对它计算
+static HOST_EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+fn host_us(instant: Instant) -> i64 {
+    instant.saturating_duration_since(*HOST_EPOCH).as_micros() as i64
+}
+
+// 设备随帧上报的序号和采集时间，可以放在二进制帧头里，也可以在图像前单独发一条文本消息
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceFrameInfo {
+    pub frame_id: u32,
+    pub capture_us: u64,
+}
+
+// 拆出帧头，没有帧头时原样返回
+pub fn split_frame_header(data: Vec<u8>) -> (Vec<u8>, Option<DeviceFrameInfo>) {
+    if data.len() <= FRAME_HEADER_LEN || &data[0..4] != FRAME_HEADER_MAGIC {
+        return (data, None);
+    }
+    let frame_id = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let capture_us = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    (data[FRAME_HEADER_LEN..].to_vec(), Some(DeviceFrameInfo { frame_id, capture_us }))
+}
+
+// 估计设备时钟到本机时钟的偏移
+// 单向传输无法区分偏移和网络延迟，取窗口内 (接收时间 - 采集时间) 的最小值，
+// 因此换算出的传输延迟是相对于最快一帧的，能反映抖动和排队，但不含固定的最小网络延迟
+#[derive(Debug, Default)]
+pub struct ClockSync {
+    samples: VecDeque<i64>,
+    offset_us: Option<i64>,
+    last_frame_id: Option<u32>,
+    last_capture_us: u64,
+}
+
+impl ClockSync {
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.offset_us = None;
+        self.last_frame_id = None;
+        self.last_capture_us = 0;
+    }
+
+    pub fn offset_us(&self) -> Option<i64> {
+        self.offset_us
+    }
+
+    // 加入一个样本，返回和上一帧之间丢失的帧数
+    pub fn update(&mut self, info: &DeviceFrameInfo, received_at: Instant) -> u64 {
+        // 设备重启后计数和时钟都会归零
+        if info.capture_us < self.last_capture_us || self.last_frame_id.map(|id| info.frame_id <= id).unwrap_or(false) {
+            self.reset();
+        }
+        let lost = self.last_frame_id
+            .map(|id| (info.frame_id - id - 1) as u64)
+            .unwrap_or(0);
+        self.last_frame_id = Some(info.frame_id);
+        self.last_capture_us = info.capture_us;
+
+        if self.samples.len() >= CLOCK_SAMPLE_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(host_us(received_at) - info.capture_us as i64);
+        self.offset_us = self.samples.iter().min().copied();
+        lost
+    }
+
+    // 设备时间换算为本机时间
+    pub fn to_host(&self, capture_us: u64) -> Option<Instant> {
+        let host = capture_us as i64 + self.offset_us?;
+        if host < 0 {
+            return None;
+        }
+        Some(*HOST_EPOCH + Duration::from_micros(host as u64))
+    }
+}
+
+// 一帧从采集到结果发布经过的各个阶段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyStage {
+    // 设备采集到本机收到
+    Transport,
+    // JPEG 解码
+    Decode,
+    // 旋转、翻转和预处理
+    Process,
+    // 模型推理
+    Inference,
+    // 结果发布给订阅者和界面，不含 OSC 发送
+    Publish,
+    // 采集（没有设备时间时为收到）到结果发布的总延迟
+    Total,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageLatency {
+    pub samples: u64,
+    pub last_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyStats {
+    pub stages: BTreeMap<LatencyStage, StageLatency>,
+    // 设备时钟到本机时钟的偏移，没有设备时间戳时为空
+    pub clock_offset_us: Option<i64>,
+    pub last_frame_id: Option<u32>,
+    // 根据帧序号判断的丢帧数
+    pub lost_frames: u64,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, stage: LatencyStage, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let entry = self.stages.entry(stage).or_default();
+        entry.mean_ms = if entry.samples == 0 {
+            latency_ms
+        } else {
+            entry.mean_ms + LATENCY_EMA_ALPHA * (latency_ms - entry.mean_ms)
+        };
+        entry.samples += 1;
+        entry.last_ms = latency_ms;
+        entry.max_ms = entry.max_ms.max(latency_ms);
+    }
+}
+
+// 随帧传递的时间信息，后续阶段据此计算延迟
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTiming {
+    pub received_at: Instant,
+    pub device: Option<DeviceFrameInfo>,
+    // 换算到本机时钟的采集时间
+    pub captured_at: Option<Instant>,
+}
+
+impl FrameTiming {
+    pub fn received(received_at: Instant) -> Self {
+        FrameTiming {
+            received_at,
+            device: None,
+            captured_at: None,
+        }
+    }
+
+    // 延迟的起点，有设备时间时用采集时间
+    pub fn origin(&self) -> Instant {
+        self.captured_at.unwrap_or(self.received_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_header(frame_id: u32, capture_us: u64, jpeg: &[u8]) -> Vec<u8> {
+        let mut data = FRAME_HEADER_MAGIC.to_vec();
+        data.extend_from_slice(&frame_id.to_le_bytes());
+        data.extend_from_slice(&capture_us.to_le_bytes());
+        data.extend_from_slice(jpeg);
+        data
+    }
+
+    #[test]
+    fn splits_frame_header() {
+        let (jpeg, info) = split_frame_header(with_header(7, 123_456, &[0xff, 0xd8, 0xff]));
+        assert_eq!(jpeg, vec![0xff, 0xd8, 0xff]);
+        assert_eq!(info, Some(DeviceFrameInfo { frame_id: 7, capture_us: 123_456 }));
+    }
+
+    #[test]
+    fn data_without_header_is_unchanged() {
+        let data = vec![0xff, 0xd8, 0xff, 0xe0];
+        assert_eq!(split_frame_header(data.clone()), (data, None));
+        // 只有帧头没有图像时不拆
+        let header_only = with_header(1, 1, &[]);
+        assert_eq!(split_frame_header(header_only.clone()), (header_only, None));
+    }
+
+    #[test]
+    fn clock_offset_uses_fastest_frame() {
+        let mut sync = ClockSync::default();
+        let base = *HOST_EPOCH + Duration::from_secs(10);
+        sync.update(&DeviceFrameInfo { frame_id: 1, capture_us: 1_000 }, base + Duration::from_micros(5_000));
+        sync.update(&DeviceFrameInfo { frame_id: 2, capture_us: 2_000 }, base + Duration::from_micros(4_000));
+        let offset = sync.offset_us().unwrap();
+        assert_eq!(offset, host_us(base) + 2_000);
+        assert_eq!(sync.to_host(2_000), Some(base + Duration::from_micros(4_000)));
+    }
+
+    #[test]
+    fn counts_lost_frames_and_resets_on_device_restart() {
+        let mut sync = ClockSync::default();
+        let now = Instant::now();
+        assert_eq!(sync.update(&DeviceFrameInfo { frame_id: 1, capture_us: 1_000 }, now), 0);
+        assert_eq!(sync.update(&DeviceFrameInfo { frame_id: 4, capture_us: 4_000 }, now), 2);
+        // 设备重启，序号和时间都回到起点
+        assert_eq!(sync.update(&DeviceFrameInfo { frame_id: 1, capture_us: 500 }, now), 0);
+        assert_eq!(sync.update(&DeviceFrameInfo { frame_id: 2, capture_us: 600 }, now), 0);
+    }
+
+    #[test]
+    fn stage_latency_tracks_last_mean_and_max() {
+        let mut stats = LatencyStats::default();
+        stats.record(LatencyStage::Decode, Duration::from_millis(10));
+        stats.record(LatencyStage::Decode, Duration::from_millis(20));
+        let decode = &stats.stages[&LatencyStage::Decode];
+        assert_eq!(decode.samples, 2);
+        assert_eq!(decode.last_ms, 20.0);
+        assert_eq!(decode.max_ms, 20.0);
+        assert!((decode.mean_ms - 11.0).abs() < 1e-9);
+    }
+}
@@ -1,2 +1,11 @@
 pub mod image_stream;
-pub mod image_msg;
\ No newline at end of file
+pub mod image_msg;
+pub mod preprocess;
+pub mod frame_hub;
+pub mod reconnect;
+pub mod frame_source;
+pub mod ws_source;
+pub mod camera_source;
+pub mod replay_source;
+pub mod stereo_sync;
+pub mod latency;
\ No newline at end of file
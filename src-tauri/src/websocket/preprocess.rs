@@ -0,0 +1,322 @@
+use opencv::{core::{Mat, MatTrait, MatTraitConst, Size, CV_8UC1}, imgproc::{self, CLAHETrait}};
+use serde::{Deserialize, Serialize};
+
+// 单个预处理步骤及其参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreprocessStep {
+    Grayscale,
+    Gamma { gamma: f64 },
+    Clahe { clip_limit: f64, tile_size: i32 },
+    GaussianBlur { kernel_size: i32, sigma: f64 },
+    Bilateral { diameter: i32, sigma_color: f64, sigma_space: f64 },
+    EqualizeHist,
+    Resize { width: i32, height: i32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PreprocessStage {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub step: PreprocessStep,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 按顺序执行的预处理链，配置为空时直接返回原图
+#[derive(Debug, Clone, Default)]
+pub struct PreprocessPipeline {
+    stages: Vec<PreprocessStage>,
+}
+
+impl PreprocessPipeline {
+    pub fn new(stages: Vec<PreprocessStage>) -> Self {
+        PreprocessPipeline { stages }
+    }
+
+    pub fn stages(&self) -> &[PreprocessStage] {
+        &self.stages
+    }
+
+    pub fn set_stages(&mut self, stages: Vec<PreprocessStage>) {
+        self.stages = stages;
+    }
+
+    pub fn set_stage_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        match self.stages.get_mut(index) {
+            Some(stage) => {
+                stage.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.stages.iter().any(|stage| stage.enabled)
+    }
+
+    pub fn apply(&self, image: &Mat) -> opencv::Result<Mat> {
+        let mut current = image.clone();
+        for stage in self.stages.iter().filter(|stage| stage.enabled) {
+            current = apply_step(&stage.step, &current)?;
+        }
+        Ok(current)
+    }
+}
+
+fn apply_step(step: &PreprocessStep, image: &Mat) -> opencv::Result<Mat> {
+    let mut output = Mat::default();
+    match step {
+        PreprocessStep::Grayscale => {
+            return to_gray(image);
+        }
+        PreprocessStep::Gamma { gamma } => {
+            let lut = gamma_lut(*gamma)?;
+            opencv::core::lut(image, &lut, &mut output)?;
+        }
+        PreprocessStep::Clahe { clip_limit, tile_size } => {
+            let gray = to_gray(image)?;
+            let tile = (*tile_size).max(1);
+            let mut clahe = imgproc::create_clahe(*clip_limit, Size::new(tile, tile))?;
+            clahe.apply(&gray, &mut output)?;
+        }
+        PreprocessStep::GaussianBlur { kernel_size, sigma } => {
+            // 高斯核尺寸必须为正奇数
+            let kernel = (*kernel_size).max(1) | 1;
+            imgproc::gaussian_blur_def(image, &mut output, Size::new(kernel, kernel), *sigma)?;
+        }
+        PreprocessStep::Bilateral { diameter, sigma_color, sigma_space } => {
+            imgproc::bilateral_filter_def(image, &mut output, *diameter, *sigma_color, *sigma_space)?;
+        }
+        PreprocessStep::EqualizeHist => {
+            let gray = to_gray(image)?;
+            imgproc::equalize_hist(&gray, &mut output)?;
+        }
+        PreprocessStep::Resize { width, height } => {
+            if *width <= 0 || *height <= 0 {
+                return Ok(image.clone());
+            }
+            imgproc::resize(image, &mut output, Size::new(*width, *height), 0.0, 0.0, imgproc::INTER_AREA)?;
+        }
+    }
+    Ok(output)
+}
+
+pub(crate) fn to_gray(image: &Mat) -> opencv::Result<Mat> {
+    if image.channels() == 1 {
+        return Ok(image.clone());
+    }
+    let mut gray = Mat::default();
+    imgproc::cvt_color_def(image, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+    Ok(gray)
+}
+
+fn gamma_lut(gamma: f64) -> opencv::Result<Mat> {
+    let gamma = if gamma > 0.0 { gamma } else { 1.0 };
+    let mut lut = Mat::new_rows_cols_with_default(1, 256, CV_8UC1, opencv::core::Scalar::all(0.0))?;
+    for i in 0..256 {
+        let value = ((i as f64 / 255.0).powf(1.0 / gamma) * 255.0).round().clamp(0.0, 255.0);
+        *lut.at_2d_mut::<u8>(0, i)? = value as u8;
+    }
+    Ok(lut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use opencv::{core::{Scalar, Vector, CV_8UC3}, imgcodecs};
+
+    // 设置该环境变量时用当前输出覆盖 golden 图，而不是比较
+    const UPDATE_GOLDEN_ENV: &str = "PAPERTRACKER_UPDATE_GOLDEN";
+
+    fn solid(rows: i32, cols: i32, bgr: [f64; 3]) -> Mat {
+        Mat::new_rows_cols_with_default(rows, cols, CV_8UC3, Scalar::new(bgr[0], bgr[1], bgr[2], 0.0)).unwrap()
+    }
+
+    fn solid_gray(rows: i32, cols: i32, value: f64) -> Mat {
+        Mat::new_rows_cols_with_default(rows, cols, CV_8UC1, Scalar::all(value)).unwrap()
+    }
+
+    fn pixel(image: &Mat, row: i32, col: i32) -> u8 {
+        *image.at_2d::<u8>(row, col).unwrap()
+    }
+
+    fn stage(step: PreprocessStep) -> PreprocessStage {
+        PreprocessStage { enabled: true, step }
+    }
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-fixtures/preprocess").join(name)
+    }
+
+    fn read_fixture(name: &str) -> Mat {
+        let path = fixture_path(name);
+        let image = imgcodecs::imread(path.to_str().unwrap(), imgcodecs::IMREAD_UNCHANGED).unwrap();
+        assert!(!image.empty(), "missing fixture {}", path.display());
+        image
+    }
+
+    // golden 图由 test-fixtures/preprocess/make_golden.py 生成，tolerance 为允许的最大像素差
+    fn assert_golden(output: &Mat, name: &str, tolerance: f64) {
+        if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            let path = fixture_path(name);
+            assert!(imgcodecs::imwrite(path.to_str().unwrap(), output, &Vector::new()).unwrap());
+            return;
+        }
+        let golden = read_fixture(name);
+        assert_eq!((output.cols(), output.rows(), output.channels()), (golden.cols(), golden.rows(), golden.channels()), "{} size mismatch", name);
+        let max_diff = opencv::core::norm2(output, &golden, opencv::core::NORM_INF, &opencv::core::no_array()).unwrap();
+        assert!(max_diff <= tolerance, "{} differs from golden by {}", name, max_diff);
+    }
+
+    fn golden_input_gray() -> Mat {
+        to_gray(&read_fixture("input.png")).unwrap()
+    }
+
+    #[test]
+    fn grayscale_converts_bgr() {
+        // 纯红 (B=0, G=0, R=255) 的灰度为 0.299 * 255
+        let output = apply_step(&PreprocessStep::Grayscale, &solid(2, 2, [0.0, 0.0, 255.0])).unwrap();
+        assert_eq!(output.channels(), 1);
+        assert_eq!(pixel(&output, 0, 0), 76);
+    }
+
+    #[test]
+    fn grayscale_keeps_gray_input() {
+        let output = apply_step(&PreprocessStep::Grayscale, &solid_gray(2, 2, 42.0)).unwrap();
+        assert_eq!(pixel(&output, 1, 1), 42);
+    }
+
+    #[test]
+    fn gamma_brightens_midtones() {
+        let output = apply_step(&PreprocessStep::Gamma { gamma: 2.0 }, &solid_gray(2, 2, 64.0)).unwrap();
+        assert_eq!(pixel(&output, 0, 0), 128);
+        // 非正数按 1 处理
+        let output = apply_step(&PreprocessStep::Gamma { gamma: 0.0 }, &solid_gray(2, 2, 64.0)).unwrap();
+        assert_eq!(pixel(&output, 0, 0), 64);
+    }
+
+    #[test]
+    fn equalize_hist_stretches_two_levels() {
+        let mut image = solid_gray(4, 4, 10.0);
+        for row in 2..4 {
+            for col in 0..4 {
+                *image.at_2d_mut::<u8>(row, col).unwrap() = 20;
+            }
+        }
+        let output = apply_step(&PreprocessStep::EqualizeHist, &image).unwrap();
+        assert_eq!(pixel(&output, 0, 0), 0);
+        assert_eq!(pixel(&output, 3, 3), 255);
+    }
+
+    #[test]
+    fn clahe_outputs_gray() {
+        let output = apply_step(&PreprocessStep::Clahe { clip_limit: 2.0, tile_size: 2 }, &solid(8, 8, [50.0, 50.0, 50.0])).unwrap();
+        assert_eq!(output.channels(), 1);
+        assert_eq!(output.rows(), 8);
+    }
+
+    #[test]
+    fn gaussian_blur_keeps_uniform_image() {
+        // 偶数核尺寸会被调整为奇数
+        let output = apply_step(&PreprocessStep::GaussianBlur { kernel_size: 4, sigma: 1.0 }, &solid_gray(8, 8, 100.0)).unwrap();
+        assert_eq!(pixel(&output, 4, 4), 100);
+    }
+
+    #[test]
+    fn resize_changes_size_and_ignores_invalid() {
+        let output = apply_step(&PreprocessStep::Resize { width: 4, height: 2 }, &solid_gray(8, 8, 100.0)).unwrap();
+        assert_eq!((output.cols(), output.rows()), (4, 2));
+        let output = apply_step(&PreprocessStep::Resize { width: 0, height: 2 }, &solid_gray(8, 8, 100.0)).unwrap();
+        assert_eq!((output.cols(), output.rows()), (8, 8));
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let red = solid(2, 2, [0.0, 0.0, 255.0]);
+        let gray_first = PreprocessPipeline::new(vec![
+            stage(PreprocessStep::Grayscale),
+            stage(PreprocessStep::Gamma { gamma: 2.0 }),
+        ]);
+        let gamma_first = PreprocessPipeline::new(vec![
+            stage(PreprocessStep::Gamma { gamma: 2.0 }),
+            stage(PreprocessStep::Grayscale),
+        ]);
+        assert_eq!(pixel(&gray_first.apply(&red).unwrap(), 0, 0), 139);
+        assert_eq!(pixel(&gamma_first.apply(&red).unwrap(), 0, 0), 76);
+    }
+
+    #[test]
+    fn disabled_stages_are_skipped() {
+        let mut pipeline = PreprocessPipeline::new(vec![
+            stage(PreprocessStep::Grayscale),
+            stage(PreprocessStep::Gamma { gamma: 2.0 }),
+        ]);
+        assert!(pipeline.set_stage_enabled(1, false));
+        assert!(!pipeline.set_stage_enabled(2, false));
+        assert_eq!(pixel(&pipeline.apply(&solid(2, 2, [0.0, 0.0, 255.0])).unwrap(), 0, 0), 76);
+        assert!(!pipeline.is_empty());
+        assert!(pipeline.set_stage_enabled(0, false));
+        assert!(pipeline.is_empty());
+        // 全部关闭时原样返回
+        let output = pipeline.apply(&solid(2, 2, [0.0, 0.0, 255.0])).unwrap();
+        assert_eq!(output.channels(), 3);
+    }
+
+    #[test]
+    fn stage_config_defaults_to_enabled() {
+        let stage: PreprocessStage = serde_json::from_str(r#"{"type": "gamma", "gamma": 2.0}"#).unwrap();
+        assert!(stage.enabled);
+        assert_eq!(stage.step, PreprocessStep::Gamma { gamma: 2.0 });
+    }
+
+    #[test]
+    fn grayscale_matches_golden() {
+        let output = apply_step(&PreprocessStep::Grayscale, &read_fixture("input.png")).unwrap();
+        assert_golden(&output, "grayscale.png", 0.0);
+    }
+
+    #[test]
+    fn gamma_matches_golden() {
+        let output = apply_step(&PreprocessStep::Gamma { gamma: 2.0 }, &golden_input_gray()).unwrap();
+        assert_golden(&output, "gamma.png", 0.0);
+    }
+
+    #[test]
+    fn equalize_hist_matches_golden() {
+        let output = apply_step(&PreprocessStep::EqualizeHist, &golden_input_gray()).unwrap();
+        assert_golden(&output, "equalize_hist.png", 1.0);
+    }
+
+    #[test]
+    fn resize_matches_golden() {
+        let output = apply_step(&PreprocessStep::Resize { width: 16, height: 12 }, &golden_input_gray()).unwrap();
+        assert_golden(&output, "resize.png", 0.0);
+    }
+
+    #[test]
+    fn gaussian_blur_matches_golden() {
+        // golden 图按浮点核计算，OpenCV 对 8 位图像使用定点核，允许少量误差
+        let output = apply_step(&PreprocessStep::GaussianBlur { kernel_size: 5, sigma: 1.0 }, &golden_input_gray()).unwrap();
+        assert_golden(&output, "gaussian_blur.png", 2.0);
+    }
+
+    #[test]
+    fn pipeline_matches_golden() {
+        let pipeline = PreprocessPipeline::new(vec![
+            stage(PreprocessStep::Grayscale),
+            stage(PreprocessStep::Gamma { gamma: 2.0 }),
+            // 关闭的步骤不影响输出
+            PreprocessStage { enabled: false, step: PreprocessStep::Bilateral { diameter: 5, sigma_color: 50.0, sigma_space: 50.0 } },
+            stage(PreprocessStep::EqualizeHist),
+            stage(PreprocessStep::Resize { width: 16, height: 12 }),
+        ]);
+        let output = pipeline.apply(&read_fixture("input.png")).unwrap();
+        assert_golden(&output, "pipeline.png", 1.0);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{collections::VecDeque, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use serde::Serialize;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+// 退避时间上下浮动的比例，避免三路设备同时重连
+const BACKOFF_JITTER: f64 = 0.2;
+// 保留最近的连接记录条数
+const HISTORY_LEN: usize = 20;
+
+// 指数退避，失败次数越多等待越久，连接成功后重置
+#[derive(Debug)]
+pub struct ReconnectBackoff {
+    failures: u32,
+    next_attempt: Instant,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        ReconnectBackoff {
+            failures: 0,
+            next_attempt: Instant::now(),
+        }
+    }
+}
+
+impl ReconnectBackoff {
+    pub fn ready(&self) -> bool {
+        Instant::now() >= self.next_attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+        self.next_attempt = Instant::now();
+    }
+
+    // 记录一次失败，返回下次重连前的等待时间
+    pub fn failure(&mut self) -> Duration {
+        let delay = backoff_delay(self.failures, jitter_unit());
+        self.failures = self.failures.saturating_add(1);
+        self.next_attempt = Instant::now() + delay;
+        delay
+    }
+}
+
+// 第 failures 次失败后的等待时间，jitter 为 [0, 1) 的随机数，0.5 时没有抖动
+fn backoff_delay(failures: u32, jitter: f64) -> Duration {
+    let exponent = failures.min(16);
+    let delay = BACKOFF_BASE.saturating_mul(1u32 << exponent).min(BACKOFF_MAX);
+    delay.mul_f64(1.0 + BACKOFF_JITTER * (2.0 * jitter - 1.0))
+}
+
+// [0, 1) 的伪随机数，只用于退避抖动
+fn jitter_unit() -> f64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+    (nanos % 1_000_000) as f64 / 1_000_000.0
+}
+
+// 看门狗：socket 没断但设备不再推流时判定连接失效
+// 只有收到帧才会续期，读超时和状态包都不算
+#[derive(Debug)]
+pub struct StaleWatchdog {
+    timeout: Duration,
+    // 连接建立或最近一次收到帧的时间
+    last_frame_at: Instant,
+    frame_since_connect: bool,
+}
+
+impl StaleWatchdog {
+    pub fn new(timeout: Duration) -> Self {
+        StaleWatchdog {
+            timeout,
+            last_frame_at: Instant::now(),
+            frame_since_connect: false,
+        }
+    }
+
+    pub fn connected(&mut self, now: Instant) {
+        self.last_frame_at = now;
+        self.frame_since_connect = false;
+    }
+
+    // 记录收到一帧，返回是否为连接后的第一帧
+    pub fn frame(&mut self, now: Instant) -> bool {
+        self.last_frame_at = now;
+        let first = !self.frame_since_connect;
+        self.frame_since_connect = true;
+        first
+    }
+
+    pub fn received_frame(&self) -> bool {
+        self.frame_since_connect
+    }
+
+    pub fn is_stale(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_frame_at) > self.timeout
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionAttempt {
+    pub time: String,
+    pub source: String,
+    pub success: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConnectionStats {
+    pub attempts: u64,
+    pub successes: u64,
+    pub failures: u64,
+    // 连接保持但长时间没有收到帧而主动断开的次数
+    pub stale_disconnects: u64,
+    pub current_backoff_ms: u64,
+    pub frames_received: u64,
+    pub history: VecDeque<ConnectionAttempt>,
+}
+
+impl ConnectionStats {
+    pub fn record(&mut self, source: String, success: bool, reason: &str) {
+        self.attempts += 1;
+        if success {
+            self.successes += 1;
+            self.current_backoff_ms = 0;
+        } else {
+            self.failures += 1;
+        }
+        if self.history.len() >= HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(ConnectionAttempt {
+            time: chrono::Local::now().to_rfc3339(),
+            source,
+            success,
+            reason: reason.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_per_failure() {
+        let delays: Vec<Duration> = (0..5).map(|failures| backoff_delay(failures, 0.5)).collect();
+        assert_eq!(delays, vec![
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+            Duration::from_secs(4),
+            Duration::from_secs(8),
+        ]);
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        assert_eq!(backoff_delay(6, 0.5), BACKOFF_MAX);
+        assert_eq!(backoff_delay(40, 0.5), BACKOFF_MAX);
+        assert_eq!(backoff_delay(u32::MAX, 0.5), BACKOFF_MAX);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        // 浮点换算可能差 1ns
+        let close = |a: Duration, b: Duration| a.max(b) - a.min(b) <= Duration::from_micros(1);
+        assert!(close(backoff_delay(1, 0.0), Duration::from_millis(800)));
+        assert!(backoff_delay(1, 0.999_999) < Duration::from_millis(1200));
+        assert!(close(backoff_delay(40, 0.0), Duration::from_secs(24)));
+        assert!(backoff_delay(40, 0.999_999) < Duration::from_secs(36));
+        for _ in 0..1000 {
+            let jitter = jitter_unit();
+            assert!((0.0..1.0).contains(&jitter));
+        }
+    }
+
+    #[test]
+    fn failure_backs_off_and_reset_restarts() {
+        let mut backoff = ReconnectBackoff::default();
+        assert!(backoff.ready());
+        let first = backoff.failure();
+        assert!(first >= Duration::from_millis(399) && first < Duration::from_millis(600));
+        assert!(!backoff.ready());
+        let second = backoff.failure();
+        assert!(second >= Duration::from_millis(799) && second < Duration::from_millis(1200));
+        backoff.reset();
+        assert!(backoff.ready());
+        let after_reset = backoff.failure();
+        assert!(after_reset >= Duration::from_millis(399) && after_reset < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn watchdog_goes_stale_without_frames() {
+        let start = Instant::now();
+        let mut watchdog = StaleWatchdog::new(Duration::from_secs(5));
+        watchdog.connected(start);
+        assert!(!watchdog.is_stale(start + Duration::from_secs(5)));
+        assert!(watchdog.is_stale(start + Duration::from_millis(5001)));
+        assert!(!watchdog.received_frame());
+    }
+
+    #[test]
+    fn watchdog_frames_keep_connection_alive() {
+        let start = Instant::now();
+        let mut watchdog = StaleWatchdog::new(Duration::from_secs(5));
+        watchdog.connected(start);
+        assert!(watchdog.frame(start + Duration::from_secs(4)));
+        assert!(!watchdog.frame(start + Duration::from_secs(8)));
+        assert!(!watchdog.is_stale(start + Duration::from_secs(12)));
+        assert!(watchdog.is_stale(start + Duration::from_millis(13_001)));
+        assert!(watchdog.received_frame());
+    }
+
+    #[test]
+    fn watchdog_reconnect_resets_first_frame() {
+        let start = Instant::now();
+        let mut watchdog = StaleWatchdog::new(Duration::from_secs(5));
+        watchdog.connected(start);
+        watchdog.frame(start);
+        let reconnect = start + Duration::from_secs(20);
+        watchdog.connected(reconnect);
+        assert!(!watchdog.received_frame());
+        assert!(!watchdog.is_stale(reconnect + Duration::from_secs(1)));
+        assert!(watchdog.frame(reconnect + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn stats_keep_recent_history() {
+        let mut stats = ConnectionStats { current_backoff_ms: 1000, ..Default::default() };
+        for i in 0..HISTORY_LEN + 5 {
+            stats.record(format!("ws{}", i), false, "refused");
+        }
+        stats.record("ws".to_string(), true, "connected");
+        assert_eq!(stats.attempts, HISTORY_LEN as u64 + 6);
+        assert_eq!(stats.failures, HISTORY_LEN as u64 + 5);
+        assert_eq!(stats.successes, 1);
+        assert_eq!(stats.current_backoff_ms, 0);
+        assert_eq!(stats.history.len(), HISTORY_LEN);
+        assert!(stats.history.back().unwrap().success);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+use ftlog::*;
+use super::frame_source::{FrameSource, SourceEvent};
+
+// 按文件名顺序回放目录中的 JPEG 帧，用于离线调试
+pub struct ReplaySource {
+    path: String,
+    fps: f64,
+    looped: bool,
+    files: Vec<PathBuf>,
+    position: usize,
+    last_frame: Option<Instant>,
+}
+
+impl ReplaySource {
+    pub fn new(path: String, fps: f64, looped: bool) -> Self {
+        ReplaySource {
+            path,
+            fps,
+            looped,
+            files: Vec::new(),
+            position: 0,
+            last_frame: None,
+        }
+    }
+
+    fn frame_interval(&self) -> Duration {
+        if self.fps > 0.0 {
+            Duration::from_secs_f64(1.0 / self.fps)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+impl FrameSource for ReplaySource {
+    fn name(&self) -> String {
+        format!("replay({})", self.path)
+    }
+
+    fn connect(&mut self) -> bool {
+        let entries = match std::fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Failed to open replay directory {}: {}", self.path, e);
+                return false;
+            }
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        if files.is_empty() {
+            error!("No JPEG frames found in replay directory {}", self.path);
+            return false;
+        }
+        info!("Replay {} loaded {} frames", self.path, files.len());
+        self.files = files;
+        self.position = 0;
+        self.last_frame = None;
+        true
+    }
+
+    fn read(&mut self) -> anyhow::Result<SourceEvent> {
+        if self.position >= self.files.len() {
+            if !self.looped || self.files.is_empty() {
+                return Ok(SourceEvent::Closed);
+            }
+            self.position = 0;
+        }
+        // 按配置帧率节流
+        if let Some(last_frame) = self.last_frame {
+            let elapsed = last_frame.elapsed();
+            let interval = self.frame_interval();
+            if elapsed < interval {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+        let data = std::fs::read(&self.files[self.position])?;
+        self.position += 1;
+        self.last_frame = Some(Instant::now());
+        Ok(SourceEvent::Jpeg(data, None))
+    }
+
+    fn disconnect(&mut self) {
+        self.files.clear();
+        self.position = 0;
+        self.last_frame = None;
+    }
+}
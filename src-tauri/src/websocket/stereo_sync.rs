@@ -0,0 +1,255 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::{Duration, Instant}};
+use crossbeam::select;
+use ftlog::*;
+use serde::Serialize;
+use super::{frame_hub::{FrameHub, FrameSubscription, SubscriberPolicy}, image_msg::Frame};
+
+// 每路最多缓存的待配对帧
+const PENDING_LEN: usize = 4;
+// 偏差均值的平滑系数
+const SKEW_EMA_ALPHA: f64 = 0.1;
+
+// 同一时刻的左右眼图像
+#[derive(Debug, Clone)]
+pub struct StereoFrame {
+    pub left: Arc<Frame>,
+    pub right: Arc<Frame>,
+    pub skew: Duration,
+    pub timestamp: Instant,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StereoStats {
+    pub pairs: u64,
+    pub dropped_left: u64,
+    pub dropped_right: u64,
+    pub tolerance_ms: f64,
+    pub last_skew_ms: f64,
+    pub mean_skew_ms: f64,
+    pub max_skew_ms: f64,
+}
+
+// 把左右眼两路独立的图像流按时间戳配对，配对结果发布给眼追消费者
+// 没有订阅者时不订阅左右眼，保证图像流可以暂停解码
+pub struct StereoSynchronizer {
+    left_hub: FrameHub,
+    right_hub: FrameHub,
+    stereo_hub: FrameHub<Arc<StereoFrame>>,
+    // 单位微秒，可运行时修改
+    tolerance_us: Arc<AtomicU64>,
+    stats: Arc<Mutex<StereoStats>>,
+    pending_left: VecDeque<Arc<Frame>>,
+    pending_right: VecDeque<Arc<Frame>>,
+}
+
+impl StereoSynchronizer {
+    pub fn new(left_hub: FrameHub, right_hub: FrameHub, tolerance_ms: f64) -> Self {
+        StereoSynchronizer {
+            left_hub,
+            right_hub,
+            stereo_hub: FrameHub::new(),
+            tolerance_us: Arc::new(AtomicU64::new((tolerance_ms.max(0.0) * 1000.0) as u64)),
+            stats: Arc::new(Mutex::new(StereoStats {
+                tolerance_ms,
+                ..Default::default()
+            })),
+            pending_left: VecDeque::new(),
+            pending_right: VecDeque::new(),
+        }
+    }
+
+    pub fn get_stereo_hub(&self) -> FrameHub<Arc<StereoFrame>> {
+        self.stereo_hub.clone()
+    }
+
+    pub fn get_tolerance(&self) -> Arc<AtomicU64> {
+        self.tolerance_us.clone()
+    }
+
+    pub fn get_stats(&self) -> Arc<Mutex<StereoStats>> {
+        self.stats.clone()
+    }
+
+    pub fn start(&mut self) {
+        let mut subscriptions: Option<(FrameSubscription, FrameSubscription)> = None;
+        loop {
+            if self.stereo_hub.subscriber_count() == 0 {
+                if subscriptions.take().is_some() {
+                    info!("Stereo synchronizer paused, no subscribers");
+                    self.pending_left.clear();
+                    self.pending_right.clear();
+                }
+                std::thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            let (left, right) = subscriptions.get_or_insert_with(|| {
+                info!("Stereo synchronizer started");
+                (
+                    self.left_hub.subscribe("stereo:left", SubscriberPolicy::DropOldest(PENDING_LEN)),
+                    self.right_hub.subscribe("stereo:right", SubscriberPolicy::DropOldest(PENDING_LEN)),
+                )
+            });
+            select! {
+                recv(left.receiver()) -> frame => {
+                    if let Ok(frame) = frame {
+                        push_pending(&mut self.pending_left, frame);
+                    }
+                }
+                recv(right.receiver()) -> frame => {
+                    if let Ok(frame) = frame {
+                        push_pending(&mut self.pending_right, frame);
+                    }
+                }
+                default(Duration::from_millis(200)) => (),
+            }
+            self.match_pending();
+        }
+    }
+
+    // 每次比较两路最早的帧：偏差在容差内则配对，否则丢弃较早的一帧（它不可能再和对侧更晚的帧配上）
+    fn match_pending(&mut self) {
+        let tolerance = Duration::from_micros(self.tolerance_us.load(Ordering::Relaxed));
+        while let (Some(left), Some(right)) = (self.pending_left.front(), self.pending_right.front()) {
+            let (left_time, right_time) = (left.capture_time(), right.capture_time());
+            let (skew, left_is_older) = if left_time <= right_time {
+                (right_time - left_time, true)
+            } else {
+                (left_time - right_time, false)
+            };
+            if skew <= tolerance {
+                let left = self.pending_left.pop_front().unwrap();
+                let right = self.pending_right.pop_front().unwrap();
+                let timestamp = left.capture_time().max(right.capture_time());
+                self.record_pair(skew, tolerance);
+                self.stereo_hub.publish(Arc::new(StereoFrame { left, right, skew, timestamp }));
+            } else if left_is_older {
+                self.pending_left.pop_front();
+                self.stats.lock().unwrap().dropped_left += 1;
+            } else {
+                self.pending_right.pop_front();
+                self.stats.lock().unwrap().dropped_right += 1;
+            }
+        }
+    }
+
+    fn record_pair(&self, skew: Duration, tolerance: Duration) {
+        let skew_ms = skew.as_secs_f64() * 1000.0;
+        let mut stats = self.stats.lock().unwrap();
+        stats.mean_skew_ms = if stats.pairs == 0 {
+            skew_ms
+        } else {
+            stats.mean_skew_ms + SKEW_EMA_ALPHA * (skew_ms - stats.mean_skew_ms)
+        };
+        stats.pairs += 1;
+        stats.last_skew_ms = skew_ms;
+        stats.max_skew_ms = stats.max_skew_ms.max(skew_ms);
+        stats.tolerance_ms = tolerance.as_secs_f64() * 1000.0;
+    }
+}
+
+fn push_pending(pending: &mut VecDeque<Arc<Frame>>, frame: Arc<Frame>) {
+    if pending.len() >= PENDING_LEN {
+        pending.pop_front();
+    }
+    pending.push_back(frame);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::Mat;
+
+    fn frame(base: Instant, ms: u64) -> Arc<Frame> {
+        Arc::new(Frame::new(Mat::default(), base + Duration::from_millis(ms)))
+    }
+
+    fn synchronizer(tolerance_ms: f64) -> StereoSynchronizer {
+        StereoSynchronizer::new(FrameHub::new(), FrameHub::new(), tolerance_ms)
+    }
+
+    #[test]
+    fn pairs_frames_within_tolerance() {
+        let base = Instant::now();
+        let mut sync = synchronizer(20.0);
+        let subscription = sync.get_stereo_hub().subscribe("test", SubscriberPolicy::DropOldest(8));
+        push_pending(&mut sync.pending_left, frame(base, 0));
+        push_pending(&mut sync.pending_right, frame(base, 5));
+        sync.match_pending();
+        let pair = subscription.try_recv().unwrap();
+        assert_eq!(pair.skew, Duration::from_millis(5));
+        assert_eq!(pair.timestamp, base + Duration::from_millis(5));
+        assert!(sync.pending_left.is_empty() && sync.pending_right.is_empty());
+        let stats = sync.get_stats().lock().unwrap().clone();
+        assert_eq!(stats.pairs, 1);
+        assert_eq!(stats.last_skew_ms, 5.0);
+    }
+
+    #[test]
+    fn drops_older_frame_that_cannot_pair() {
+        let base = Instant::now();
+        let mut sync = synchronizer(10.0);
+        let subscription = sync.get_stereo_hub().subscribe("test", SubscriberPolicy::DropOldest(8));
+        // 左眼 0ms 与右眼 30ms 偏差过大，丢弃较早的左眼帧后 33ms 与 30ms 配对
+        push_pending(&mut sync.pending_left, frame(base, 0));
+        push_pending(&mut sync.pending_left, frame(base, 33));
+        push_pending(&mut sync.pending_right, frame(base, 30));
+        sync.match_pending();
+        let pair = subscription.try_recv().unwrap();
+        assert_eq!(pair.left.timestamp, base + Duration::from_millis(33));
+        assert_eq!(pair.right.timestamp, base + Duration::from_millis(30));
+        assert!(subscription.try_recv().is_err());
+        let stats = sync.get_stats().lock().unwrap().clone();
+        assert_eq!((stats.pairs, stats.dropped_left, stats.dropped_right), (1, 1, 0));
+    }
+
+    #[test]
+    fn drops_older_right_frame() {
+        let base = Instant::now();
+        let mut sync = synchronizer(10.0);
+        push_pending(&mut sync.pending_left, frame(base, 50));
+        push_pending(&mut sync.pending_right, frame(base, 0));
+        push_pending(&mut sync.pending_right, frame(base, 20));
+        sync.match_pending();
+        // 两个右眼帧都太早，左眼帧留着等待下一帧
+        let stats = sync.get_stats().lock().unwrap().clone();
+        assert_eq!((stats.pairs, stats.dropped_left, stats.dropped_right), (0, 0, 2));
+        assert_eq!(sync.pending_left.len(), 1);
+        assert!(sync.pending_right.is_empty());
+    }
+
+    #[test]
+    fn tolerance_can_change_at_runtime() {
+        let base = Instant::now();
+        let mut sync = synchronizer(5.0);
+        sync.get_tolerance().store(30_000, Ordering::Relaxed);
+        push_pending(&mut sync.pending_left, frame(base, 0));
+        push_pending(&mut sync.pending_right, frame(base, 25));
+        sync.match_pending();
+        let stats = sync.get_stats().lock().unwrap().clone();
+        assert_eq!(stats.pairs, 1);
+        assert_eq!(stats.tolerance_ms, 30.0);
+    }
+
+    #[test]
+    fn pending_queue_is_bounded() {
+        let base = Instant::now();
+        let mut pending = VecDeque::new();
+        for ms in 0..PENDING_LEN as u64 + 3 {
+            push_pending(&mut pending, frame(base, ms));
+        }
+        assert_eq!(pending.len(), PENDING_LEN);
+        assert_eq!(pending.front().unwrap().timestamp, base + Duration::from_millis(3));
+    }
+
+    #[test]
+    fn skew_stats_track_mean_and_max() {
+        let mut sync = synchronizer(20.0);
+        let tolerance = Duration::from_millis(20);
+        sync.record_pair(Duration::from_millis(10), tolerance);
+        sync.record_pair(Duration::from_millis(0), tolerance);
+        let stats = sync.get_stats().lock().unwrap().clone();
+        assert_eq!(stats.pairs, 2);
+        assert_eq!(stats.max_skew_ms, 10.0);
+        assert!((stats.mean_skew_ms - 9.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,172 @@
+use ftlog::*;
+use std::{io::ErrorKind, time::Duration};
+use tungstenite::{connect, Message, WebSocket};
+use url::Url;
+use crate::utils::consts::{DEVICE_TYPE_FACE, DEVICE_TYPE_LEFT_EYE, DEVICE_TYPE_RIGHT_EYE};
+use super::{frame_source::{FrameSource, SourceEvent}, image_msg::DeviceStatus, latency::{split_frame_header, DeviceFrameInfo}};
+
+// 读超时只是让读取循环定期让出，去处理设置请求和串口消息
+// 与看门狗的失效时间无关，超时按没有数据处理
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+type WsPort = WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>;
+
+pub struct WebSocketSource {
+    device_type: i32,
+    ip: String,
+    port: Option<WsPort>,
+    // 设备在图像前单独发送的帧信息，附加到下一帧
+    pending_info: Option<DeviceFrameInfo>,
+}
+
+impl WebSocketSource {
+    pub fn new(device_type: i32, ip: String) -> Self {
+        WebSocketSource {
+            device_type,
+            ip,
+            port: None,
+            pending_info: None,
+        }
+    }
+
+    pub fn get_connect_url_from_self_ip(&self) -> String {
+        if !self.ip.starts_with("ws://") && !self.ip.starts_with("wss://") {
+            if self.ip.starts_with("http://") {
+                format!("ws://{}/ws", self.ip.strip_prefix("http://").unwrap_or(&self.ip))
+            } else if self.ip.starts_with("https://") {
+                format!("wss://{}/ws", self.ip.strip_prefix("https://").unwrap_or(&self.ip))
+            } else {
+                // Assume host:port format
+                let mut ws_url = format!("ws://{}", self.ip);
+                if !ws_url.contains(':') {
+                    ws_url.push_str(":80");
+                }
+                if !ws_url.contains("/ws") {
+                    ws_url.push_str("/ws");
+                }
+                ws_url
+            }
+        } else {
+            self.ip.clone()
+        }
+    }
+}
+
+impl FrameSource for WebSocketSource {
+    fn name(&self) -> String {
+        format!("websocket({})", self.ip)
+    }
+
+    fn connect(&mut self) -> bool {
+        let mut ip_list = Vec::new();
+        match self.device_type {
+            DEVICE_TYPE_FACE => {
+                ip_list.push("ws://paper1.local:80/ws");
+            },
+            DEVICE_TYPE_LEFT_EYE => {
+                ip_list.push("ws://paper2.local:80/ws");
+            }
+            DEVICE_TYPE_RIGHT_EYE => {
+                ip_list.push("ws://paper3.local:80/ws");
+            }
+            _ => {
+                error!("Unknown device type: {}", self.device_type);
+            }
+        }
+        // make ip live longer
+        let ip_formated = self.get_connect_url_from_self_ip();
+        if !self.ip.is_empty() {
+            ip_list.push(ip_formated.as_str());
+        }
+
+        for url_to_try in ip_list {
+            info!("Stream {} Trying to connect to: {}", self.device_type, url_to_try);
+            match Url::parse(url_to_try) {
+                Ok(url) => {
+                    match connect(url) {
+                        Ok((mut ws, _)) => {
+                            info!("Stream {} Connected to: {}", self.device_type, url_to_try);
+
+                            if let tungstenite::stream::MaybeTlsStream::Plain(stream) = ws.get_mut() {
+                                if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+                                    warn!("Stream {} Failed to set read timeout: {}", self.device_type, e);
+                                }
+                            }
+                            self.port = Some(ws);
+                            return true;
+                        }
+                        Err(e) => {
+                            error!("Stream {} Failed to connect to {}: {}", self.device_type, url_to_try, e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Stream {} Invalid URL {}: {}", self.device_type, url_to_try, e);
+                }
+            }
+        }
+
+        self.port.is_some()
+    }
+
+    fn read(&mut self) -> anyhow::Result<SourceEvent> {
+        let port = match self.port {
+            Some(ref mut port) => port,
+            None => return Err(anyhow::anyhow!("websocket is not connected")),
+        };
+        let message = match port.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(SourceEvent::Idle);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match message {
+            Message::Binary(data) => {
+                // Process image data
+                if data.len() < 10 {
+                    warn!("Received binary data too small to be an image");
+                    return Ok(SourceEvent::Idle);
+                }
+                let (data, info) = split_frame_header(data);
+                Ok(SourceEvent::Jpeg(data, info.or(self.pending_info.take())))
+            }
+            Message::Text(text) => {
+                if let Ok(info) = serde_json::from_str::<DeviceFrameInfo>(&text) {
+                    self.pending_info = Some(info);
+                    return Ok(SourceEvent::Idle);
+                }
+                match serde_json::from_str::<DeviceStatus>(&text) {
+                    Ok(status) => Ok(SourceEvent::Status(status)),
+                    Err(e) => {
+                        warn!("Failed to parse status message: {}, message: {}", e, text);
+                        Ok(SourceEvent::Idle)
+                    }
+                }
+            }
+            Message::Close(_) => Ok(SourceEvent::Closed),
+            _ => Ok(SourceEvent::Idle),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(mut port) = self.port.take() {
+            let _ = port.close(None);
+        }
+        self.pending_info = None;
+    }
+
+    fn set_address(&mut self, address: &str) {
+        self.ip = address.to_string();
+    }
+
+    fn send_text(&mut self, text: &str) -> anyhow::Result<()> {
+        match self.port {
+            Some(ref mut port) => {
+                port.send(Message::Text(text.to_string()))?;
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("websocket is not connected")),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+# 生成 add_one.onnx：y = x + 1，x 形状为 [N, 3]，N 为动态维度
+# 直接手写 protobuf 编码，不依赖 onnx 包：python3 make_add_one.py
+import struct
+from pathlib import Path
+
+
+def varint(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field(number, wire_type, payload):
+    return varint(number << 3 | wire_type) + payload
+
+
+def int_field(number, value):
+    return field(number, 0, varint(value))
+
+
+def bytes_field(number, payload):
+    if isinstance(payload, str):
+        payload = payload.encode()
+    return field(number, 2, varint(len(payload)) + payload)
+
+
+FLOAT = 1
+
+
+def value_info(name):
+    dims = bytes_field(1, bytes_field(2, "N")) + bytes_field(1, int_field(1, 3))
+    tensor_type = int_field(1, FLOAT) + bytes_field(2, dims)
+    return bytes_field(1, name) + bytes_field(2, bytes_field(1, tensor_type))
+
+
+node = bytes_field(1, "x") + bytes_field(1, "one") + bytes_field(2, "y") + bytes_field(3, "add") + bytes_field(4, "Add")
+one = int_field(2, FLOAT) + bytes_field(4, struct.pack("<f", 1.0)) + bytes_field(8, "one")
+graph = (
+    bytes_field(1, node)
+    + bytes_field(2, "add_one")
+    + bytes_field(5, one)
+    + bytes_field(11, value_info("x"))
+    + bytes_field(12, value_info("y"))
+)
+model = int_field(1, 7) + bytes_field(2, "papertracker-tests") + bytes_field(7, graph) + bytes_field(8, bytes_field(1, "") + int_field(2, 13))
+
+Path(__file__).with_name("add_one.onnx").write_bytes(model)
@@ -0,0 +1,122 @@
+# 生成预处理测试用的输入图和 golden 图：python3 make_golden.py
+# 只用标准库，按 OpenCV 8 位图像的整数算法计算，结果应与 OpenCV 逐像素一致（高斯模糊除外，允许少量误差）
+# 也可以用 PAPERTRACKER_UPDATE_GOLDEN=1 cargo test 由 OpenCV 直接重新生成 golden 图
+import math
+import struct
+import zlib
+from pathlib import Path
+
+WIDTH = 32
+HEIGHT = 24
+
+
+def write_png(path, width, height, channels, rows):
+    color_type = {1: 0, 3: 2}[channels]
+    raw = b"".join(b"\x00" + bytes(row) for row in rows)
+
+    def chunk(kind, data):
+        return struct.pack(">I", len(data)) + kind + data + struct.pack(">I", zlib.crc32(kind + data) & 0xFFFFFFFF)
+
+    header = struct.pack(">IIBBBBB", width, height, 8, color_type, 0, 0, 0)
+    png = b"\x89PNG\r\n\x1a\n" + chunk(b"IHDR", header) + chunk(b"IDAT", zlib.compress(raw, 9)) + chunk(b"IEND", b"")
+    Path(path).write_bytes(png)
+
+
+def write_gray(path, image):
+    write_png(path, len(image[0]), len(image), 1, image)
+
+
+# 输入图：三个通道各自是不同方向的渐变，再叠加一个暗色圆，模拟红外眼图中的瞳孔
+def make_input():
+    image = []
+    for y in range(HEIGHT):
+        row = []
+        for x in range(WIDTH):
+            b = x * 8
+            g = y * 10
+            r = (x * 3 + y * 5) % 256
+            if (x - 20) ** 2 + (y - 12) ** 2 <= 36:
+                b, g, r = b // 4, g // 4, r // 4
+            row.append((b, g, r))
+        image.append(row)
+    return image
+
+
+# COLOR_BGR2GRAY 的 14 位定点系数
+def to_gray(image):
+    return [[(b * 1868 + g * 9617 + r * 4899 + (1 << 13)) >> 14 for b, g, r in row] for row in image]
+
+
+def gamma(image, value):
+    lut = [min(255, max(0, math.floor((i / 255.0) ** (1.0 / value) * 255.0 + 0.5))) for i in range(256)]
+    return [[lut[p] for p in row] for row in image]
+
+
+def f32(value):
+    return struct.unpack("f", struct.pack("f", value))[0]
+
+
+# 与 cv::equalizeHist 相同：单精度缩放系数，cvRound 取整
+def equalize_hist(image):
+    hist = [0] * 256
+    for row in image:
+        for p in row:
+            hist[p] += 1
+    total = sum(hist)
+    first = next(i for i in range(256) if hist[i])
+    if hist[first] == total:
+        return [[first for _ in row] for row in image]
+    scale = f32(255.0 / (total - hist[first]))
+    lut = [0] * 256
+    acc = 0
+    for i in range(first + 1, 256):
+        acc += hist[i]
+        lut[i] = min(255, max(0, round(f32(f32(acc) * scale))))
+    return [[lut[p] for p in row] for row in image]
+
+
+# INTER_AREA 整数倍缩小一半：2x2 求和后四舍五入
+def half(image):
+    return [
+        [(image[y][x] + image[y][x + 1] + image[y + 1][x] + image[y + 1][x + 1] + 2) >> 2 for x in range(0, len(image[0]), 2)]
+        for y in range(0, len(image), 2)
+    ]
+
+
+def reflect101(index, size):
+    if index < 0:
+        return -index
+    if index >= size:
+        return 2 * size - index - 2
+    return index
+
+
+# 浮点版高斯模糊，OpenCV 对 8 位图像使用定点核，结果可能差 1 到 2
+def gaussian(image, ksize, sigma):
+    radius = ksize // 2
+    kernel = [math.exp(-((i - radius) ** 2) / (2 * sigma * sigma)) for i in range(ksize)]
+    norm = sum(kernel)
+    kernel = [k / norm for k in kernel]
+    height, width = len(image), len(image[0])
+    rows = [[sum(kernel[i] * image[y][reflect101(x + i - radius, width)] for i in range(ksize)) for x in range(width)] for y in range(height)]
+    return [
+        [min(255, max(0, math.floor(sum(kernel[i] * rows[reflect101(y + i - radius, height)][x] for i in range(ksize)) + 0.5))) for x in range(width)]
+        for y in range(height)
+    ]
+
+
+def main():
+    out = Path(__file__).parent
+    image = make_input()
+    write_png(out / "input.png", WIDTH, HEIGHT, 3, [[c for b, g, r in row for c in (r, g, b)] for row in image])
+    gray = to_gray(image)
+    write_gray(out / "grayscale.png", gray)
+    write_gray(out / "gamma.png", gamma(gray, 2.0))
+    write_gray(out / "equalize_hist.png", equalize_hist(gray))
+    write_gray(out / "resize.png", half(gray))
+    write_gray(out / "gaussian_blur.png", gaussian(gray, 5, 1.0))
+    write_gray(out / "pipeline.png", half(equalize_hist(gamma(gray, 2.0))))
+
+
+if __name__ == "__main__":
+    main()
@@ -1,9 +1,13 @@
 // 定义对应的 TypeScript 类型
-export interface ImageEvent {
-    type: 'image';
+// 图像本身通过 papertracker://localhost/<handle> 获取
+export interface FrameEvent {
+    type: 'frame';
     data: {
-        base64: string;
         device: string;
+        handle: number;
+        seq: number;
+        width: number;
+        height: number;
     };
 }
   
@@ -12,6 +16,7 @@ export interface StatusEvent {
     data: {
         ip: string;
         serial: boolean;
+        wifi: boolean;
         battery: number;
         brightness: number;
         device_type: number; // 设备类型
@@ -25,7 +30,32 @@ export interface LogEvent {
     };
 }
   
-export type StreamEvent = ImageEvent | StatusEvent | LogEvent;
+export type StreamEvent = FrameEvent | StatusEvent | LogEvent;
+
+// device_status 事件与预览通道的 status 内容相同
+export type DeviceStatus = StatusEvent['data'];
+
+// face_blendshapes 事件的一项，数值范围 [0, 1]
+export interface BlendshapeValue {
+    name: string;
+    value: number;
+}
+
+// get_calibration 返回的单个表情参数，curve 为响应曲线
+export interface ExpressionParams {
+    offset: number;
+    gain: number;
+    curve: { type: string; [key: string]: unknown };
+}
+
+// get_eye_calibration_status 返回的校准进度
+export interface EyeCalibrationStatus {
+    step: 'look_around' | 'center';
+    look_around_samples: number;
+    center_samples: number;
+    look_around_required: number;
+    center_required: number;
+}
 
 // 定义消息类型
 export interface ImageMessage {